@@ -39,7 +39,8 @@
 use automerge::{
     marks::{ExpandMark, Mark},
     transaction::Transactable,
-    Automerge, AutomergeError, Change, ChangeHash, ObjId, Patch, ReadDoc, ScalarValue, Value, ROOT,
+    Automerge, AutomergeError, Change, ChangeHash, ObjId, Patch, PatchAction, ReadDoc, ScalarValue,
+    Value, ROOT,
 };
 use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
@@ -49,6 +50,49 @@ use serde_json::Value as JsonValue;
 /// See SECURITY_AUDIT.md #4.
 pub const MAX_JSON_DEPTH: usize = 256;
 
+/// Magic prefix of a document saved by [`RedisAutomergeClient::save_compressed`].
+/// It is followed by a one-byte codec id and the compressed document.
+/// Automerge's own format starts with different magic bytes, so a plain save
+/// can never be mistaken for a compressed one.
+pub const COMPRESSED_SAVE_MAGIC: &[u8; 3] = b"AMZ";
+
+/// Codec id for gzip in a compressed save header.
+pub const CODEC_GZIP: u8 = 1;
+
+/// Actor id of the single change in a
+/// [`save_canonical`](RedisAutomergeClient::save_canonical) document. Fixed,
+/// along with the change's zero timestamp, so equal states save to equal
+/// bytes.
+pub const CANONICAL_ACTOR: [u8; 16] = [0; 16];
+
+/// Largest document a compressed save may inflate to, so a small blob
+/// cannot expand without bound on load. Matches the `AM.LOAD` payload cap.
+pub const MAX_DECOMPRESSED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Root key holding the elements of a document built by
+/// [`RedisAutomergeClient::list_slice`]. An Automerge root is always a map,
+/// so the list cannot be the root itself.
+pub const LIST_SLICE_KEY: &str = "items";
+
+/// Suffix of the sibling key that holds an expiring field's deadline, e.g.
+/// `presence.alice__expires` for `presence.alice`. See
+/// [`RedisAutomergeClient::put_text_expiring`].
+pub const EXPIRES_SUFFIX: &str = "__expires";
+
+/// Keys of the single-key objects that stand for a counter, a timestamp and
+/// a byte string in [`RedisAutomergeClient::to_json_tagged`] output.
+pub const COUNTER_TAG: &str = "$counter";
+pub const TIMESTAMP_TAG: &str = "$timestamp";
+pub const BYTES_TAG: &str = "$bytes";
+
+/// Key of the single-key object that wraps a map whose only key is itself a
+/// tag name in [`RedisAutomergeClient::to_json_tagged`] output, so the map
+/// is not read back as the tag: `{"$map":{"$counter":5}}` is the map
+/// `{"$counter":5}`.
+pub const MAP_TAG: &str = "$map";
+
+const TAGS: [&str; 4] = [COUNTER_TAG, TIMESTAMP_TAG, BYTES_TAG, MAP_TAG];
+
 /// Represents a diff operation parsed from unified diff format
 #[derive(Debug, PartialEq)]
 enum DiffOp {
@@ -60,6 +104,22 @@ enum DiffOp {
     Add(String),
 }
 
+/// One hunk of a unified diff: the ops between one `@@` header and the next.
+#[derive(Debug, PartialEq)]
+struct DiffHunk {
+    /// Index into the old text's lines where the hunk starts, from the
+    /// `@@ -start,count` header. `None` for lines given without any header,
+    /// which apply where the previous hunk left off.
+    old_start: Option<usize>,
+    ops: Vec<DiffOp>,
+    /// A `\ No newline at end of file` marker followed the hunk's last
+    /// old-side line (context or delete).
+    old_no_newline: bool,
+    /// The same marker followed the hunk's last new-side line (context or
+    /// add).
+    new_no_newline: bool,
+}
+
 /// Error type returned by `put_diff` / `put_diff_with_change`. Wraps the
 /// generic `AutomergeError` and adds richly-typed variants for diff
 /// application failures so the caller (and ultimately the redis-cli user)
@@ -93,6 +153,13 @@ pub enum DiffError {
         line_num: usize,
         op: &'static str,
     },
+    /// A `\ No newline at end of file` marker did not match the document:
+    /// it was attached to a line that is not the last one, or claimed the
+    /// old text lacked a final newline when it has one.
+    NewlineMismatch { line_num: usize },
+    /// The diff itself is not well formed: a bad `@@` header, a hunk whose
+    /// lines don't add up to its header's counts, or hunks that overlap.
+    Malformed(String),
 }
 
 impl std::fmt::Display for DiffError {
@@ -127,6 +194,13 @@ impl std::fmt::Display for DiffError {
                  than the diff expects)",
                 op, line_num
             ),
+            DiffError::NewlineMismatch { line_num } => write!(
+                f,
+                "diff newline mismatch at line {}: the \"No newline at end of \
+                 file\" marker does not match the document",
+                line_num
+            ),
+            DiffError::Malformed(reason) => write!(f, "malformed diff: {}", reason),
         }
     }
 }
@@ -139,6 +213,293 @@ impl From<AutomergeError> for DiffError {
     }
 }
 
+/// Error type returned by `verify`. Describes how a document failed to
+/// survive a save/reload round trip.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The saved bytes could not be loaded back into a document.
+    Reload(AutomergeError),
+    /// The reloaded document has different heads from the original, i.e.
+    /// some history was lost or altered by serialization.
+    HeadsMismatch {
+        expected: Vec<ChangeHash>,
+        actual: Vec<ChangeHash>,
+    },
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |hashes: &[ChangeHash]| {
+            hashes
+                .iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        match self {
+            VerifyError::Reload(e) => write!(
+                f,
+                "document failed to reload from its own saved form: {}",
+                e
+            ),
+            VerifyError::HeadsMismatch { expected, actual } => write!(
+                f,
+                "document heads diverged after save/reload: expected [{}], found [{}]",
+                join(expected),
+                join(actual)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Magic prefix identifying an `AM.DUMP` export.
+pub const DUMP_MAGIC: &[u8; 6] = b"AMDUMP";
+
+/// Layout version of the `AM.DUMP` format written by [`RedisAutomergeClient::dump`].
+pub const DUMP_FORMAT_VERSION: u8 = 1;
+
+/// Error type returned by `RedisAutomergeClient::import`.
+#[derive(Debug)]
+pub enum DumpError {
+    /// The blob does not start with [`DUMP_MAGIC`].
+    BadMagic,
+    /// The blob was written in a format version this build cannot read.
+    UnsupportedVersion(u8),
+    /// The blob ends early, has bytes left over after the last change, or
+    /// its changes depend on history it does not contain.
+    Malformed(&'static str),
+    /// A change failed to decode or apply.
+    Automerge(AutomergeError),
+}
+
+impl std::fmt::Display for DumpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpError::BadMagic => write!(f, "not an AM.DUMP blob (bad magic)"),
+            DumpError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported AM.DUMP format version {} (this build reads version {})",
+                v, DUMP_FORMAT_VERSION
+            ),
+            DumpError::Malformed(what) => write!(f, "malformed AM.DUMP blob: {}", what),
+            DumpError::Automerge(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+impl From<AutomergeError> for DumpError {
+    fn from(e: AutomergeError) -> Self {
+        DumpError::Automerge(e)
+    }
+}
+
+/// Split `n` bytes off the front of `buf` while decoding an `AM.DUMP` blob.
+fn take_bytes<'a>(buf: &mut &'a [u8], n: usize, what: &'static str) -> Result<&'a [u8], DumpError> {
+    if buf.len() < n {
+        return Err(DumpError::Malformed(what));
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+/// Concatenate changes as big-endian `u32 len + raw change bytes` frames,
+/// the framing `AM.DUMP` uses for its change list. This is the reply of
+/// `AM.CHANGES <key> PACKED`; [`unpack_changes`] splits it again.
+pub fn pack_changes(changes: &[Change]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for change in changes {
+        let raw = change.raw_bytes();
+        out.extend_from_slice(&(raw.len() as u32).to_be_bytes());
+        out.extend_from_slice(raw);
+    }
+    out
+}
+
+/// Split a [`pack_changes`] blob back into changes, in order.
+///
+/// # Errors
+///
+/// Returns [`DumpError::Malformed`] if a frame is cut short and
+/// [`DumpError::Automerge`] if a change does not decode.
+pub fn unpack_changes(mut packed: &[u8]) -> Result<Vec<Change>, DumpError> {
+    let mut changes = Vec::new();
+    while !packed.is_empty() {
+        changes.push(take_framed_change(&mut packed)?);
+    }
+    Ok(changes)
+}
+
+/// Decode one `u32 len + raw change bytes` frame off the front of `buf`.
+fn take_framed_change(buf: &mut &[u8]) -> Result<Change, DumpError> {
+    let len = take_bytes(buf, 4, "change length")?;
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let raw = take_bytes(buf, len, "change")?;
+    Ok(Change::from_bytes(raw.to_vec()).map_err(AutomergeError::from)?)
+}
+
+/// Where a document's saved size goes, as reported by
+/// [`RedisAutomergeClient::mem_breakdown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemBreakdown {
+    /// Size of the full `save()` output, history included.
+    pub total_bytes: usize,
+    /// Size of a save holding only the current state in a single change.
+    pub state_bytes: usize,
+    /// `total_bytes - state_bytes`: what history costs on top of the state.
+    pub history_bytes: usize,
+    /// Number of operations in the document.
+    pub ops: usize,
+    /// Number of changes in the change graph.
+    pub changes: usize,
+    /// Number of actors that have written changes.
+    pub actors: usize,
+}
+
+/// The change that wrote a value, as reported by
+/// [`RedisAutomergeClient::last_writer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastWriter {
+    /// Hex id of the actor that made the change.
+    pub actor: String,
+    /// Hash of the change.
+    pub hash: ChangeHash,
+    /// The change's timestamp as its author recorded it, in seconds since
+    /// the Unix epoch, or 0 if it recorded none.
+    pub timestamp: i64,
+}
+
+/// One operation from the change history, as reported by
+/// [`RedisAutomergeClient::path_ops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathOp {
+    /// `insert`, `put`, `make`, `delete`, `increment` or `mark`.
+    pub action: &'static str,
+    /// Hex id of the actor that made the change.
+    pub actor: String,
+    /// The change's sequence number among that actor's changes.
+    pub seq: u64,
+    /// The value written, object type made, increment or mark name, as
+    /// text; `None` for ops that carry nothing.
+    pub value: Option<String>,
+}
+
+/// How [`RedisAutomergeClient::list_query`] compares a field with the
+/// requested value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOp {
+    /// Equal, as [`RedisAutomergeClient::list_find`] matches.
+    Eq,
+    /// Not equal.
+    Ne,
+    /// Less than.
+    Lt,
+    /// Greater than.
+    Gt,
+}
+
+/// How [`RedisAutomergeClient::list_contains`] compares list elements with
+/// the value it looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMatch {
+    /// Same type and equal value, so `5` never matches `"5"`. Used by the
+    /// `NX` appends.
+    Exact,
+    /// A string value matches as [`RedisAutomergeClient::list_find`]
+    /// matches fields: text exactly, numbers when the string parses to the
+    /// same number, and booleans `true`/`false` in any case. Other values
+    /// match as with [`Exact`](Self::Exact). Used by `AM.LISTCONTAINS`.
+    Loose,
+}
+
+/// Unit a client counts text positions and lengths in. Automerge itself
+/// indexes text by Unicode scalar value, so positions in other units are
+/// converted before use. JavaScript strings count UTF-16 code units, where
+/// an emoji outside the Basic Multilingual Plane takes two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextUnit {
+    /// Unicode scalar values, Automerge's own unit.
+    #[default]
+    CodePoints,
+    /// UTF-16 code units.
+    Utf16,
+    /// UTF-8 bytes.
+    Bytes,
+}
+
+impl TextUnit {
+    /// Width of `c` in this unit.
+    fn width(self, c: char) -> usize {
+        match self {
+            TextUnit::CodePoints => 1,
+            TextUnit::Utf16 => c.len_utf16(),
+            TextUnit::Bytes => c.len_utf8(),
+        }
+    }
+
+    /// Length of `text` in this unit.
+    pub fn len(self, text: &str) -> usize {
+        text.chars().map(|c| self.width(c)).sum()
+    }
+
+    /// Convert `pos`, counted in this unit, to a Unicode scalar offset into
+    /// `text`.
+    ///
+    /// Fails with `InvalidCharacter` if `pos` falls inside a character (the
+    /// middle of a surrogate pair or of a multi-byte UTF-8 sequence), and
+    /// with `InvalidIndex` if it is past the end of `text`.
+    pub fn to_char_offset(self, text: &str, pos: usize) -> Result<usize, AutomergeError> {
+        let mut at = 0;
+        for (i, c) in text.chars().enumerate() {
+            if at == pos {
+                return Ok(i);
+            }
+            at += self.width(c);
+            if at > pos {
+                return Err(AutomergeError::InvalidCharacter(pos));
+            }
+        }
+        if at == pos {
+            Ok(text.chars().count())
+        } else {
+            Err(AutomergeError::InvalidIndex(pos))
+        }
+    }
+}
+
+/// Split the first Automerge storage chunk off the front of `buf`.
+///
+/// A chunk is 4 magic bytes, a 4-byte checksum, a type byte, the data
+/// length as unsigned LEB128, then the data. Returns `None` if `buf` does
+/// not start with a complete chunk header and body; the contents are not
+/// validated here.
+fn split_chunk(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut pos = 9;
+    let mut len: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *buf.get(pos)?;
+        pos += 1;
+        len |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift >= 64 {
+            return None;
+        }
+    }
+    let end = pos.checked_add(usize::try_from(len).ok()?)?;
+    if end > buf.len() {
+        return None;
+    }
+    Some(buf.split_at(end))
+}
+
 /// Represents a typed value extracted from an Automerge document
 /// This is used for building JSON index documents with proper types
 #[derive(Debug, Clone, PartialEq)]
@@ -154,6 +515,20 @@ pub enum TypedValue {
     Null,
 }
 
+impl From<&ScalarValue> for TypedValue {
+    fn from(value: &ScalarValue) -> Self {
+        match value {
+            ScalarValue::Str(text) => TypedValue::Text(text.to_string()),
+            ScalarValue::Int(i) => TypedValue::Int(*i),
+            ScalarValue::F64(f) => TypedValue::Double(*f),
+            ScalarValue::Boolean(b) => TypedValue::Bool(*b),
+            ScalarValue::Timestamp(ts) => TypedValue::Timestamp(*ts),
+            ScalarValue::Counter(c) => TypedValue::Counter(i64::from(c)),
+            _ => TypedValue::Null,
+        }
+    }
+}
+
 impl TypedValue {
     /// Convert TypedValue to serde_json::Value for JSON serialization
     pub fn to_json(&self) -> JsonValue {
@@ -188,29 +563,303 @@ impl TypedValue {
     }
 }
 
-/// Parse a unified diff into operations
-fn parse_unified_diff(diff: &str) -> Result<Vec<DiffOp>, AutomergeError> {
-    let mut ops = Vec::new();
+/// A scalar as plain text for diagnostics: strings unquoted, bytes as hex.
+fn scalar_text(value: &ScalarValue) -> String {
+    match value {
+        ScalarValue::Str(s) => s.to_string(),
+        ScalarValue::Bytes(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        ScalarValue::Int(n) | ScalarValue::Timestamp(n) => n.to_string(),
+        ScalarValue::Uint(n) => n.to_string(),
+        ScalarValue::F64(n) => n.to_string(),
+        ScalarValue::Counter(c) => i64::from(c).to_string(),
+        ScalarValue::Boolean(b) => b.to_string(),
+        ScalarValue::Null => "null".to_string(),
+        ScalarValue::Unknown { bytes, .. } => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+/// The type name of a container, as `AM.OBJTYPE` and
+/// [`RedisAutomergeClient::schema`] report it. Tables read as maps.
+pub fn obj_type_name(obj_type: automerge::ObjType) -> &'static str {
+    match obj_type {
+        automerge::ObjType::Map | automerge::ObjType::Table => "map",
+        automerge::ObjType::List => "list",
+        automerge::ObjType::Text => "text",
+    }
+}
+
+/// The type name of a scalar, as [`RedisAutomergeClient::schema`] and
+/// patch notifications report it.
+pub fn scalar_type_name(value: &ScalarValue) -> &'static str {
+    match value {
+        ScalarValue::Str(_) => "str",
+        ScalarValue::Int(_) => "int",
+        ScalarValue::Uint(_) => "uint",
+        ScalarValue::F64(_) => "f64",
+        ScalarValue::Counter(_) => "counter",
+        ScalarValue::Timestamp(_) => "timestamp_ms",
+        ScalarValue::Boolean(_) => "bool",
+        ScalarValue::Bytes(_) => "bytes",
+        ScalarValue::Null => "null",
+        ScalarValue::Unknown { .. } => "unknown",
+    }
+}
+
+/// The type name of any value: [`obj_type_name`] for containers and
+/// [`scalar_type_name`] for scalars.
+fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(obj_type) => obj_type_name(*obj_type),
+        Value::Scalar(s) => scalar_type_name(s),
+    }
+}
+
+/// Which group of mutually comparable values a scalar belongs to for
+/// [`RedisAutomergeClient::list_sort`], or `None` if it has no natural
+/// order.
+fn sort_kind(value: &ScalarValue) -> Option<u8> {
+    match value {
+        ScalarValue::Int(_)
+        | ScalarValue::Uint(_)
+        | ScalarValue::F64(_)
+        | ScalarValue::Counter(_) => Some(0),
+        ScalarValue::Str(_) => Some(1),
+        ScalarValue::Timestamp(_) => Some(2),
+        ScalarValue::Boolean(_) => Some(3),
+        _ => None,
+    }
+}
+
+/// Natural order of two scalars of the same [`sort_kind`]. Integers compare
+/// exactly; a pair involving a double compares as doubles.
+fn compare_scalars(a: &ScalarValue, b: &ScalarValue) -> std::cmp::Ordering {
+    fn integer(value: &ScalarValue) -> Option<i128> {
+        match value {
+            ScalarValue::Int(i) => Some(i128::from(*i)),
+            ScalarValue::Uint(u) => Some(i128::from(*u)),
+            ScalarValue::Counter(c) => Some(i128::from(i64::from(c))),
+            _ => None,
+        }
+    }
+    match (a, b) {
+        (ScalarValue::Str(a), ScalarValue::Str(b)) => a.as_str().cmp(b.as_str()),
+        (ScalarValue::Timestamp(a), ScalarValue::Timestamp(b)) => a.cmp(b),
+        (ScalarValue::Boolean(a), ScalarValue::Boolean(b)) => a.cmp(b),
+        _ => match (integer(a), integer(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a_int, b_int) => {
+                let as_f64 = |int: Option<i128>, value: &ScalarValue| match (int, value) {
+                    (Some(i), _) => i as f64,
+                    (None, ScalarValue::F64(f)) => *f,
+                    _ => 0.0,
+                };
+                as_f64(a_int, a).total_cmp(&as_f64(b_int, b))
+            }
+        },
+    }
+}
+
+/// How a stored scalar orders against `value`, a string given on the
+/// command line: numerically for numbers when `value` parses as one,
+/// by bytes for strings, and `None` for anything else.
+fn scalar_cmp_str(scalar: &ScalarValue, value: &str) -> Option<std::cmp::Ordering> {
+    let integer = match scalar {
+        ScalarValue::Str(s) => return Some(s.as_str().cmp(value)),
+        ScalarValue::Int(i) | ScalarValue::Timestamp(i) => i128::from(*i),
+        ScalarValue::Uint(u) => i128::from(*u),
+        ScalarValue::Counter(c) => i128::from(i64::from(c)),
+        ScalarValue::F64(f) => return f.partial_cmp(&value.parse::<f64>().ok()?),
+        _ => return None,
+    };
+    match value.parse::<i128>() {
+        Ok(other) => Some(integer.cmp(&other)),
+        Err(_) => (integer as f64).partial_cmp(&value.parse::<f64>().ok()?),
+    }
+}
+
+/// Whether a stored scalar equals `value`, a string given on the command
+/// line. Strings must match exactly; integers, counters, timestamps and
+/// doubles match when `value` parses to the same number; booleans match
+/// `true`/`false` in any case.
+fn scalar_matches_str(scalar: &ScalarValue, value: &str) -> bool {
+    match scalar {
+        ScalarValue::Str(s) => s.as_str() == value,
+        ScalarValue::Int(i) | ScalarValue::Timestamp(i) => value.parse::<i64>() == Ok(*i),
+        ScalarValue::Uint(u) => value.parse::<u64>() == Ok(*u),
+        ScalarValue::Counter(c) => value.parse::<i64>() == Ok(i64::from(c)),
+        ScalarValue::F64(f) => value.parse::<f64>() == Ok(*f),
+        ScalarValue::Boolean(b) => value.eq_ignore_ascii_case(if *b { "true" } else { "false" }),
+        _ => false,
+    }
+}
+
+/// Parse the `-start[,count] +start[,count]` ranges of a `@@` hunk header,
+/// returning the old side's start and count. A missing count means 1.
+fn parse_hunk_header(header: &str) -> Result<(usize, usize, usize), DiffError> {
+    let malformed = || DiffError::Malformed(format!("bad hunk header {:?}", header));
+    let ranges = header
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(malformed)?;
+    let mut parts = ranges.split(' ');
+    let mut range = |sign: char| -> Result<(usize, usize), DiffError> {
+        let spec = parts
+            .next()
+            .and_then(|p| p.strip_prefix(sign))
+            .ok_or_else(malformed)?;
+        let (start, count) = match spec.split_once(',') {
+            Some((start, count)) => (start, count.parse().map_err(|_| malformed())?),
+            None => (spec, 1),
+        };
+        Ok((start.parse().map_err(|_| malformed())?, count))
+    };
+    let (old_start, old_count) = range('-')?;
+    let (_new_start, new_count) = range('+')?;
+    Ok((old_start, old_count, new_count))
+}
+
+/// Parse a unified diff into hunks.
+///
+/// Each `@@` header's line counts decide where its hunk ends, so lines
+/// between hunks (`diff --git`, `index`, `---`/`+++` file headers) are
+/// skipped, and a hunk line that itself starts with `---` is still read as
+/// a deletion. Within a hunk an empty line counts as an empty context
+/// line, since some tools strip the leading space. A diff with no `@@`
+/// headers at all is read as a single hunk applied from the first line.
+fn parse_unified_diff(diff: &str) -> Result<Vec<DiffHunk>, DiffError> {
+    let has_headers = diff.lines().any(|line| line.starts_with("@@"));
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    // Old/new lines still expected by the current hunk's header.
+    let mut remaining = (0usize, 0usize);
+
+    if !has_headers {
+        hunks.push(DiffHunk {
+            old_start: None,
+            ops: Vec::new(),
+            old_no_newline: false,
+            new_no_newline: false,
+        });
+    }
 
     for line in diff.lines() {
-        // Skip header lines
-        if line.starts_with("---") || line.starts_with("+++") || line.starts_with("@@") {
+        if line.starts_with("@@") {
+            if remaining != (0, 0) {
+                return Err(DiffError::Malformed(
+                    "hunk has fewer lines than its header says".to_string(),
+                ));
+            }
+            let (old_start, old_count, new_count) = parse_hunk_header(line)?;
+            // `-0,0` inserts before the first line; `-5,0` inserts after
+            // line 5. Otherwise `start` is the 1-based first old line.
+            let old_start = match (old_start, old_count) {
+                (start, 0) => start,
+                (0, _) => return Err(DiffError::Malformed(format!("bad hunk header {:?}", line))),
+                (start, _) => start - 1,
+            };
+            hunks.push(DiffHunk {
+                old_start: Some(old_start),
+                ops: Vec::new(),
+                old_no_newline: false,
+                new_no_newline: false,
+            });
+            remaining = (old_count, new_count);
+            continue;
+        }
+
+        let hunk = match hunks.last_mut() {
+            Some(hunk) => hunk,
+            // Preamble before the first header
+            None => continue,
+        };
+
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" applies to the line before it.
+            match hunk.ops.last() {
+                Some(DiffOp::Context(_)) => {
+                    hunk.old_no_newline = true;
+                    hunk.new_no_newline = true;
+                }
+                Some(DiffOp::Delete(_)) => hunk.old_no_newline = true,
+                Some(DiffOp::Add(_)) => hunk.new_no_newline = true,
+                None => {
+                    return Err(DiffError::Malformed(
+                        "no-newline marker before any hunk line".to_string(),
+                    ))
+                }
+            }
+            continue;
+        }
+
+        if hunk.old_start.is_none() {
+            // Header-less diff: skip file headers, treat unprefixed
+            // lines as context (for compatibility).
+            if line.starts_with("---") || line.starts_with("+++") {
+                continue;
+            }
+            if let Some(stripped) = line.strip_prefix('-') {
+                hunk.ops.push(DiffOp::Delete(stripped.to_string()));
+            } else if let Some(stripped) = line.strip_prefix('+') {
+                hunk.ops.push(DiffOp::Add(stripped.to_string()));
+            } else if let Some(stripped) = line.strip_prefix(' ') {
+                hunk.ops.push(DiffOp::Context(stripped.to_string()));
+            } else if !line.is_empty() {
+                hunk.ops.push(DiffOp::Context(line.to_string()));
+            }
             continue;
         }
 
+        if remaining == (0, 0) {
+            // Between hunks: file headers and other noise.
+            continue;
+        }
+        let too_long =
+            || DiffError::Malformed("hunk has more lines than its header says".to_string());
         if let Some(stripped) = line.strip_prefix('-') {
-            ops.push(DiffOp::Delete(stripped.to_string()));
+            remaining.0 = remaining.0.checked_sub(1).ok_or_else(too_long)?;
+            hunk.ops.push(DiffOp::Delete(stripped.to_string()));
         } else if let Some(stripped) = line.strip_prefix('+') {
-            ops.push(DiffOp::Add(stripped.to_string()));
-        } else if let Some(stripped) = line.strip_prefix(' ') {
-            ops.push(DiffOp::Context(stripped.to_string()));
-        } else if !line.is_empty() {
-            // Treat lines without prefix as context (for compatibility)
-            ops.push(DiffOp::Context(line.to_string()));
+            remaining.1 = remaining.1.checked_sub(1).ok_or_else(too_long)?;
+            hunk.ops.push(DiffOp::Add(stripped.to_string()));
+        } else {
+            remaining.0 = remaining.0.checked_sub(1).ok_or_else(too_long)?;
+            remaining.1 = remaining.1.checked_sub(1).ok_or_else(too_long)?;
+            let text = line.strip_prefix(' ').unwrap_or(line);
+            hunk.ops.push(DiffOp::Context(text.to_string()));
         }
     }
 
-    Ok(ops)
+    if remaining != (0, 0) {
+        return Err(DiffError::Malformed(
+            "hunk has fewer lines than its header says".to_string(),
+        ));
+    }
+    Ok(hunks)
+}
+
+/// A hashable stand-in for a scalar that is equal for two scalars exactly
+/// when they have the same type and value: a type tag followed by the
+/// value's bytes. Floats are keyed by their bits, with `-0.0` folded into
+/// `0.0`.
+fn scalar_identity(value: &ScalarValue) -> (u8, Vec<u8>) {
+    match value {
+        ScalarValue::Str(s) => (0, s.as_bytes().to_vec()),
+        ScalarValue::Int(i) => (1, i.to_be_bytes().to_vec()),
+        ScalarValue::Uint(u) => (2, u.to_be_bytes().to_vec()),
+        ScalarValue::F64(f) => {
+            let f = if *f == 0.0 { 0.0 } else { *f };
+            (3, f.to_bits().to_be_bytes().to_vec())
+        }
+        ScalarValue::Counter(c) => (4, i64::from(c).to_be_bytes().to_vec()),
+        ScalarValue::Timestamp(t) => (5, t.to_be_bytes().to_vec()),
+        ScalarValue::Boolean(b) => (6, vec![u8::from(*b)]),
+        ScalarValue::Bytes(b) => (7, b.clone()),
+        ScalarValue::Null => (8, Vec::new()),
+        ScalarValue::Unknown { type_code, bytes } => {
+            let mut key = vec![*type_code];
+            key.extend_from_slice(bytes);
+            (9, key)
+        }
+    }
 }
 
 /// Represents a path segment - either a map key or a list index
@@ -220,12 +869,39 @@ enum PathSegment {
     Index(usize),
 }
 
+/// Parse the content of a `[...]` path segment as a list index.
+///
+/// Surrounding whitespace is ignored. The index must be plain decimal
+/// digits with no sign and no leading zeros (`0` itself is fine), so that
+/// each index has exactly one spelling. Anything else is rejected with an
+/// error naming the offending segment.
+fn parse_bracket_index(content: &str) -> Result<usize, AutomergeError> {
+    let digits = content.trim();
+    let invalid = |expected: &str| AutomergeError::InvalidValueType {
+        expected: expected.to_string(),
+        unexpected: format!("path segment [{}]", content),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid("a non-negative list index"));
+    }
+    if digits.len() > 1 && digits.starts_with('0') {
+        return Err(invalid("a list index without leading zeros"));
+    }
+    digits
+        .parse::<usize>()
+        .map_err(|_| invalid("a list index within range"))
+}
+
 /// Parse a JSON-like path into components.
 /// Supports:
 /// - "foo.bar" or "$.foo.bar" for map keys
 /// - "foo[0]" or "$.foo[0]" for array indices
 /// - "foo[0].bar" for mixed paths
 ///
+/// Bracket indices may be padded with whitespace (`foo[ 0 ]`) but must
+/// otherwise be plain digits without leading zeros; see
+/// [`parse_bracket_index`].
+///
 /// Returns a vector of path segments.
 fn parse_path(path: &str) -> Result<Vec<PathSegment>, AutomergeError> {
     let trimmed = path.strip_prefix("$.").unwrap_or(path);
@@ -255,9 +931,7 @@ fn parse_path(path: &str) -> Result<Vec<PathSegment>, AutomergeError> {
                 bracket_content.clear();
             }
             ']' if in_bracket => {
-                let index = bracket_content
-                    .parse::<usize>()
-                    .map_err(|_| AutomergeError::Fail)?;
+                let index = parse_bracket_index(&bracket_content)?;
                 segments.push(PathSegment::Index(index));
                 in_bracket = false;
                 bracket_content.clear();
@@ -334,8 +1008,8 @@ fn navigate_or_create_path<T: Transactable>(
 
 /// Navigate to a nested object in the document for reading.
 /// Returns None if any part of the path doesn't exist.
-fn navigate_path_read(
-    doc: &Automerge,
+fn navigate_path_read<T: ReadDoc>(
+    doc: &T,
     path: &[PathSegment],
 ) -> Result<Option<ObjId>, AutomergeError> {
     let mut current = ROOT;
@@ -362,6 +1036,40 @@ fn navigate_path_read(
     Ok(Some(current))
 }
 
+/// Place `value` in the projection `node` at the position `path` names,
+/// creating objects for key segments and arrays for index segments. Array
+/// positions skipped over are filled with `null`.
+fn insert_projected(node: &mut JsonValue, path: &[PathSegment], value: JsonValue) {
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *node = value;
+            return;
+        }
+    };
+    let child = match (first, &mut *node) {
+        (PathSegment::Key(key), JsonValue::Object(map)) => {
+            map.entry(key.clone()).or_insert(JsonValue::Null)
+        }
+        (PathSegment::Index(idx), JsonValue::Array(items)) => {
+            if items.len() <= *idx {
+                items.resize(*idx + 1, JsonValue::Null);
+            }
+            &mut items[*idx]
+        }
+        // A placeholder from an earlier path; give it the right shape.
+        (PathSegment::Key(_), slot) => {
+            *slot = JsonValue::Object(serde_json::Map::new());
+            return insert_projected(slot, path, value);
+        }
+        (PathSegment::Index(_), slot) => {
+            *slot = JsonValue::Array(Vec::new());
+            return insert_projected(slot, path, value);
+        }
+    };
+    insert_projected(child, rest, value);
+}
+
 /// Helper to get a value from a parent object using a path segment
 fn get_value_from_parent<'a, T: ReadDoc>(
     doc: &'a T,
@@ -393,80 +1101,804 @@ fn put_value_to_parent<T: Transactable, V: Into<ScalarValue>>(
     }
 }
 
-/// Convenience methods for integrating Automerge with Redis persistence layers.
+/// Deep-copy `value` into `parent` at `segment`.
 ///
-/// AOF replication is *not* handled here. Each AM.* write command in
-/// `lib.rs` calls `ctx.replicate(cmd, args)` via `finalize_write_meta`,
-/// which causes Redis to append the original command to the AOF file;
-/// on restart, the command is replayed by its normal handler. See
-/// audit #9 in `SECURITY_AUDIT.md` for the analysis of why a per-client
-/// change buffer was unnecessary.
-pub trait RedisAutomergeExt {
-    /// Load an Automerge document from its persisted binary form.
-    ///
-    /// This is typically used when restoring a document from Redis' RDB
-    /// persistence format.
-    fn load(bytes: &[u8]) -> Result<Self, AutomergeError>
-    where
-        Self: Sized;
+/// `src` is the object id of `value` in `src_doc` when it is a container;
+/// scalars ignore it. With `src_doc` set to `None` the source is read from
+/// `tx`'s own document. Containers are recreated as new objects with the
+/// same type and their contents are copied by [`copy_object_contents`], so
+/// the copy has a fresh object identity (concurrent edits made against the
+/// original object by other replicas do not follow it). When `insert` is
+/// true and `segment` is a list index, the value is inserted at that index
+/// instead of overwriting it.
+fn copy_value_to_parent<R: ReadDoc, T: Transactable>(
+    src_doc: Option<&R>,
+    tx: &mut T,
+    value: &Value,
+    src: &ObjId,
+    parent: &ObjId,
+    segment: &PathSegment,
+    insert: bool,
+) -> Result<(), AutomergeError> {
+    match value {
+        Value::Scalar(s) => {
+            // Counters are re-seeded from their current total; the source
+            // counter's increment history stays with the original.
+            let scalar = match s.as_ref() {
+                ScalarValue::Counter(c) => ScalarValue::counter(i64::from(c)),
+                other => other.clone(),
+            };
+            match segment {
+                PathSegment::Index(idx) if insert => {
+                    tx.insert(parent, *idx, scalar)?;
+                }
+                _ => put_value_to_parent(tx, parent, segment, scalar)?,
+            }
+        }
+        Value::Object(obj_type) => {
+            let new_obj = match segment {
+                PathSegment::Key(key) => tx.put_object(parent, key.as_str(), *obj_type)?,
+                PathSegment::Index(idx) if insert => tx.insert_object(parent, *idx, *obj_type)?,
+                PathSegment::Index(idx) => tx.put_object(parent, *idx, *obj_type)?,
+            };
+            copy_object_contents(src_doc, src, tx, &new_obj)?;
+        }
+    }
+    Ok(())
+}
 
-    /// Save the current state of the document to a compact binary
-    /// representation suitable for RDB persistence.
-    fn save(&self) -> Vec<u8>;
+/// The visible contents of an object, read in full before any of it is
+/// written so that the source may live in the document being written.
+enum ObjContents {
+    /// Map entries or list elements, each with the segment it is copied to.
+    Children(Vec<(PathSegment, Value<'static>, ObjId)>),
+    /// Text content and its marks.
+    Text(String, Vec<Mark>),
+}
 
-    /// Apply a list of changes to the document.
-    fn apply(&mut self, changes: Vec<Change>) -> Result<(), AutomergeError>;
+impl ObjContents {
+    /// Read `obj` from `doc`. For a list only the elements at `range` are
+    /// read, renumbered from 0; `None` reads them all.
+    fn read<D: ReadDoc>(
+        doc: &D,
+        obj: &ObjId,
+        range: Option<std::ops::Range<usize>>,
+    ) -> Result<Self, AutomergeError> {
+        let mut children = Vec::new();
+        match doc.object_type(obj)? {
+            automerge::ObjType::Map | automerge::ObjType::Table => {
+                for key in doc.keys(obj) {
+                    if let Some((value, id)) = doc.get(obj, key.as_str())? {
+                        children.push((PathSegment::Key(key), value.into_owned(), id));
+                    }
+                }
+            }
+            automerge::ObjType::List => {
+                for i in range.unwrap_or(0..doc.length(obj)) {
+                    if let Some((value, id)) = doc.get(obj, i)? {
+                        let segment = PathSegment::Index(children.len());
+                        children.push((segment, value.into_owned(), id));
+                    }
+                }
+            }
+            automerge::ObjType::Text => {
+                return Ok(ObjContents::Text(doc.text(obj)?, doc.marks(obj)?));
+            }
+        }
+        Ok(ObjContents::Children(children))
+    }
 }
 
-/// Client for managing an Automerge CRDT document with Redis-specific features.
-///
-/// This struct wraps an Automerge document and provides:
-/// - Path-based access to nested data structures (maps and lists)
-/// - Type-safe operations for common data types
-///
-/// AOF persistence is handled at the Redis command level (each AM.*
-/// write replicates its originating command via `ctx.replicate`); the
-/// client does not carry a per-instance change buffer.
-///
-/// # Examples
-///
-/// ```rust,no_run
-/// use redis_automerge::ext::RedisAutomergeClient;
-///
-/// let mut client = RedisAutomergeClient::new();
-///
-/// // Work with nested maps
-/// client.put_text("config.host", "localhost").unwrap();
-/// client.put_int("config.port", 6379).unwrap();
-///
-/// // Work with lists
-/// client.create_list("tags").unwrap();
-/// client.append_text("tags", "redis").unwrap();
-/// client.append_text("tags", "crdt").unwrap();
+/// Estimated content size of a scalar for
+/// [`RedisAutomergeClient::heavy_paths`]: the bytes of strings and byte
+/// values, 8 for numbers and 1 for anything else.
+fn scalar_size(value: &ScalarValue) -> usize {
+    match value {
+        ScalarValue::Str(s) => s.len(),
+        ScalarValue::Bytes(b) | ScalarValue::Unknown { bytes: b, .. } => b.len(),
+        ScalarValue::Int(_)
+        | ScalarValue::Uint(_)
+        | ScalarValue::F64(_)
+        | ScalarValue::Counter(_)
+        | ScalarValue::Timestamp(_) => 8,
+        ScalarValue::Boolean(_) | ScalarValue::Null => 1,
+    }
+}
+
+/// Copy the current contents of `src` in `src_doc` (or in `tx`'s own
+/// document when `None`) into `dst`, a new, empty object of the same type
+/// in `tx`'s document.
 ///
-/// // Retrieve values
-/// let host = client.get_text("config.host").unwrap();
-/// let tag = client.get_text("tags[0]").unwrap();
-/// ```
-pub struct RedisAutomergeClient {
-    doc: Automerge,
+/// Only the visible state is copied: conflicting values lose to the winner
+/// and counters are re-seeded from their current totals. Text marks are
+/// copied with the default expand behaviour, since the original setting is
+/// not readable back from the document.
+fn copy_object_contents<R: ReadDoc, T: Transactable>(
+    src_doc: Option<&R>,
+    src: &ObjId,
+    tx: &mut T,
+    dst: &ObjId,
+) -> Result<(), AutomergeError> {
+    copy_contents(src_doc, src, None, tx, dst)
 }
 
-impl RedisAutomergeClient {
-    /// Creates a new client with an empty Automerge document.
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use redis_automerge::ext::RedisAutomergeClient;
-    ///
-    /// let client = RedisAutomergeClient::new();
-    /// ```
-    pub fn new() -> Self {
-        Self {
-            doc: Automerge::new(),
+/// Shared body of [`copy_object_contents`] and [`copy_list_range`].
+fn copy_contents<R: ReadDoc, T: Transactable>(
+    src_doc: Option<&R>,
+    src: &ObjId,
+    range: Option<std::ops::Range<usize>>,
+    tx: &mut T,
+    dst: &ObjId,
+) -> Result<(), AutomergeError> {
+    let contents = match src_doc {
+        Some(doc) => ObjContents::read(doc, src, range)?,
+        None => ObjContents::read(tx, src, range)?,
+    };
+    match contents {
+        ObjContents::Children(children) => {
+            for (segment, value, child) in children {
+                copy_value_to_parent(src_doc, tx, &value, &child, dst, &segment, true)?;
+            }
+        }
+        ObjContents::Text(text, marks) => {
+            tx.splice_text(dst, 0, 0, &text)?;
+            for mark in marks {
+                tx.mark(dst, mark, ExpandMark::default())?;
+            }
         }
     }
+    Ok(())
+}
+
+/// Merge the JSON object `json` into the existing map `map`.
+///
+/// Keys whose JSON value is an object and whose current value is a map are
+/// merged recursively; every other key is overwritten with the JSON value
+/// via [`put_json_to_parent`], so arrays and scalars replace wholesale. Keys
+/// absent from `json` are left alone.
+fn merge_json_into_map<T: Transactable>(
+    tx: &mut T,
+    json: &serde_json::Map<String, JsonValue>,
+    map: &ObjId,
+    tagged: bool,
+    depth: usize,
+) -> Result<(), AutomergeError> {
+    if depth > MAX_JSON_DEPTH {
+        return Err(AutomergeError::Fail);
+    }
+    for (key, child) in json {
+        let existing_map = match (json_map_fields(child, tagged)?, tx.get(map, key.as_str())?) {
+            (Some(fields), Some((Value::Object(automerge::ObjType::Map), id))) => {
+                Some((id, fields))
+            }
+            _ => None,
+        };
+        match existing_map {
+            Some((id, fields)) => {
+                merge_json_into_map(tx, fields, &id, tagged, depth + 1)?;
+            }
+            None => {
+                let segment = PathSegment::Key(key.clone());
+                put_tagged_json_to_parent(tx, child, map, &segment, false, tagged, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether map key `key` holding `value` is the [`EXPIRES_SUFFIX`]
+/// deadline of a sibling field. Whole-document and map readers skip these,
+/// as Redis keeps a key's TTL out of its value.
+fn is_expiry_companion(key: &str, value: &Value) -> bool {
+    match value {
+        Value::Scalar(s) => {
+            key.ends_with(EXPIRES_SUFFIX) && matches!(s.as_ref(), ScalarValue::Timestamp(_))
+        }
+        Value::Object(_) => false,
+    }
+}
+
+/// Whether `field` of map `obj` has an [`EXPIRES_SUFFIX`] deadline at or
+/// before `now_ms`.
+fn deadline_passed<R: ReadDoc>(
+    doc: &R,
+    obj: &ObjId,
+    field: &str,
+    now_ms: i64,
+) -> Result<bool, AutomergeError> {
+    let companion = format!("{}{}", field, EXPIRES_SUFFIX);
+    match doc.get(obj, companion.as_str())? {
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Timestamp(t) => Ok(*t <= now_ms),
+            _ => Ok(false),
+        },
+        _ => Ok(false),
+    }
+}
+
+/// Whether map key `key` of `obj`, holding `value`, is left out of a read
+/// of the whole map at `now_ms`: either it is an expiry companion, or its
+/// deadline has passed and the reaper has not deleted it yet.
+fn is_hidden_field<R: ReadDoc>(
+    doc: &R,
+    obj: &ObjId,
+    key: &str,
+    value: &Value,
+    now_ms: i64,
+) -> Result<bool, AutomergeError> {
+    if is_expiry_companion(key, value) {
+        return Ok(true);
+    }
+    deadline_passed(doc, obj, key, now_ms)
+}
+
+/// Collect `(map, field, path)` triples under `obj`, which sits at `base`,
+/// whose [`EXPIRES_SUFFIX`] companion holds a timestamp at or before
+/// `now_ms`.
+fn collect_expired<R: ReadDoc>(
+    doc: &R,
+    obj: &ObjId,
+    base: &str,
+    now_ms: i64,
+    out: &mut Vec<(ObjId, String, String)>,
+) -> Result<(), AutomergeError> {
+    let join = |key: &str| {
+        if base.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", base, key)
+        }
+    };
+    match doc.object_type(obj)? {
+        automerge::ObjType::Map | automerge::ObjType::Table => {
+            for key in doc.keys(obj) {
+                match doc.get(obj, key.as_str())? {
+                    Some((Value::Object(_), child)) => {
+                        collect_expired(doc, &child, &join(&key), now_ms, out)?;
+                    }
+                    Some((Value::Scalar(s), _)) => {
+                        if let (Some(field), ScalarValue::Timestamp(t)) =
+                            (key.strip_suffix(EXPIRES_SUFFIX), s.as_ref())
+                        {
+                            if *t <= now_ms {
+                                out.push((obj.clone(), field.to_string(), join(field)));
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        automerge::ObjType::List => {
+            for i in 0..doc.length(obj) {
+                if let Some((Value::Object(_), child)) = doc.get(obj, i)? {
+                    let path = format!("{}[{}]", base, i);
+                    collect_expired(doc, &child, &path, now_ms, out)?;
+                }
+            }
+        }
+        automerge::ObjType::Text => {}
+    }
+    Ok(())
+}
+
+/// Resolve Redis-style inclusive `start`/`stop` list positions against a
+/// list of `len` elements: negative values count from the end and
+/// out-of-range values are clamped, as in `LRANGE` and `LTRIM`.
+fn list_range(len: usize, start: i64, stop: i64) -> std::ops::Range<usize> {
+    let len = i64::try_from(len).unwrap_or(i64::MAX);
+    let first = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start.min(len)
+    };
+    let end = if stop < 0 {
+        len + stop + 1
+    } else {
+        stop.saturating_add(1)
+    };
+    let end = end.clamp(first, len);
+    // Both bounds now lie within 0..=len, which came from a usize.
+    first as usize..end as usize
+}
+
+/// Append the elements of list `src` at positions `range` to the empty list
+/// `dst`, copying nested objects as [`copy_object_contents`] does.
+fn copy_list_range<R: ReadDoc, T: Transactable>(
+    src_doc: &R,
+    src: &ObjId,
+    range: std::ops::Range<usize>,
+    tx: &mut T,
+    dst: &ObjId,
+) -> Result<(), AutomergeError> {
+    copy_contents(Some(src_doc), src, Some(range), tx, dst)
+}
+
+/// Write `json` at the path `segments` inside a transaction, as
+/// [`RedisAutomergeClient::put_json`] does. The root only accepts an object.
+fn write_json_at<T: Transactable>(
+    tx: &mut T,
+    segments: &[PathSegment],
+    json: &JsonValue,
+    merge: bool,
+    tagged: bool,
+) -> Result<(), AutomergeError> {
+    match segments.split_last() {
+        None => {
+            let fields = json_map_fields(json, tagged)?.ok_or(AutomergeError::Fail)?;
+            if !merge {
+                let keys: Vec<String> = tx.keys(&ROOT).collect();
+                for key in keys {
+                    tx.delete(&ROOT, key.as_str())?;
+                }
+            }
+            merge_json_into_map(tx, fields, &ROOT, tagged, 1)
+        }
+        Some((last, parent_path)) => {
+            let parent_obj = navigate_or_create_path(tx, parent_path)?;
+            let existing_map = match get_value_from_parent(&*tx, &parent_obj, last)? {
+                Some((Value::Object(automerge::ObjType::Map), id)) if merge => Some(id),
+                _ => None,
+            };
+            match (existing_map, json_map_fields(json, tagged)?) {
+                (Some(id), Some(fields)) => merge_json_into_map(tx, fields, &id, tagged, 1),
+                _ => put_tagged_json_to_parent(tx, json, &parent_obj, last, false, tagged, 1),
+            }
+        }
+    }
+}
+
+/// Delete the entry `last` of the object at `parent_segments` inside a
+/// transaction. Returns whether anything was there to delete.
+fn delete_at<T: Transactable>(
+    tx: &mut T,
+    parent_segments: &[PathSegment],
+    last: &PathSegment,
+) -> Result<bool, AutomergeError> {
+    let parent = match navigate_path_read(&*tx, parent_segments)? {
+        Some(parent) => parent,
+        None => return Ok(false),
+    };
+    match (tx.object_type(&parent)?, last) {
+        (automerge::ObjType::Map, PathSegment::Key(key))
+            if tx.get(&parent, key.as_str())?.is_some() =>
+        {
+            tx.delete(&parent, key.as_str())?;
+        }
+        (automerge::ObjType::List, PathSegment::Index(idx)) if *idx < tx.length(&parent) => {
+            tx.delete(&parent, *idx)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Materialize `json` into `parent` at `segment`.
+///
+/// Objects become maps, arrays become lists, strings are stored as scalar
+/// strings and numbers as integers when they fit an `i64` (doubles
+/// otherwise), matching [`RedisAutomergeClient::from_json`]. When `insert` is
+/// true and `segment` is a list index, the value is inserted at that index
+/// instead of overwriting it. Nesting deeper than [`MAX_JSON_DEPTH`] fails.
+fn put_json_to_parent<T: Transactable>(
+    tx: &mut T,
+    json: &JsonValue,
+    parent: &ObjId,
+    segment: &PathSegment,
+    insert: bool,
+    depth: usize,
+) -> Result<(), AutomergeError> {
+    put_tagged_json_to_parent(tx, json, parent, segment, insert, false, depth)
+}
+
+/// Like [`put_json_to_parent`], but when `tagged` is true the objects
+/// [`tagged_scalar`] recognizes are stored as the scalar they tag, and a
+/// [`MAP_TAG`] object as the map it wraps.
+fn put_tagged_json_to_parent<T: Transactable>(
+    tx: &mut T,
+    json: &JsonValue,
+    parent: &ObjId,
+    segment: &PathSegment,
+    insert: bool,
+    tagged: bool,
+    depth: usize,
+) -> Result<(), AutomergeError> {
+    if depth > MAX_JSON_DEPTH {
+        return Err(AutomergeError::Fail);
+    }
+    let scalar = match json {
+        JsonValue::Object(fields) if tagged => tagged_scalar(fields)?,
+        JsonValue::Object(_) | JsonValue::Array(_) => None,
+        JsonValue::String(s) => Some(ScalarValue::Str(s.as_str().into())),
+        JsonValue::Number(n) => Some(match n.as_i64() {
+            Some(i) => ScalarValue::Int(i),
+            None => ScalarValue::F64(n.as_f64().ok_or(AutomergeError::Fail)?),
+        }),
+        JsonValue::Bool(b) => Some(ScalarValue::Boolean(*b)),
+        JsonValue::Null => Some(ScalarValue::Null),
+    };
+    if let Some(scalar) = scalar {
+        match segment {
+            PathSegment::Index(idx) if insert => {
+                tx.insert(parent, *idx, scalar)?;
+            }
+            _ => put_value_to_parent(tx, parent, segment, scalar)?,
+        }
+        return Ok(());
+    }
+    let obj_type = match json {
+        JsonValue::Array(_) => automerge::ObjType::List,
+        _ => automerge::ObjType::Map,
+    };
+    let new_obj = match segment {
+        PathSegment::Key(key) => tx.put_object(parent, key.as_str(), obj_type)?,
+        PathSegment::Index(idx) if insert => tx.insert_object(parent, *idx, obj_type)?,
+        PathSegment::Index(idx) => tx.put_object(parent, *idx, obj_type)?,
+    };
+    match json {
+        JsonValue::Object(_) => {
+            let map = json_map_fields(json, tagged)?.ok_or(AutomergeError::Fail)?;
+            for (key, child) in map {
+                let segment = PathSegment::Key(key.clone());
+                put_tagged_json_to_parent(tx, child, &new_obj, &segment, false, tagged, depth + 1)?;
+            }
+        }
+        JsonValue::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                let segment = PathSegment::Index(i);
+                put_tagged_json_to_parent(tx, child, &new_obj, &segment, true, tagged, depth + 1)?;
+            }
+        }
+        _ => unreachable!("scalars return above"),
+    }
+    Ok(())
+}
+
+/// The scalar a tagged object written by
+/// [`RedisAutomergeClient::to_json_tagged`] stands for, or `None` when
+/// `fields` isn't a single [`COUNTER_TAG`], [`TIMESTAMP_TAG`] or
+/// [`BYTES_TAG`] key. A tag whose value has the wrong shape fails.
+fn tagged_scalar(
+    fields: &serde_json::Map<String, JsonValue>,
+) -> Result<Option<ScalarValue>, AutomergeError> {
+    let (tag, value) = match fields.iter().next() {
+        Some(entry) if fields.len() == 1 => entry,
+        _ => return Ok(None),
+    };
+    let as_i64 = || match value {
+        JsonValue::Number(n) => n.as_i64().ok_or(AutomergeError::Fail),
+        JsonValue::String(s) => s.parse().map_err(|_| AutomergeError::Fail),
+        _ => Err(AutomergeError::Fail),
+    };
+    Ok(Some(match tag.as_str() {
+        COUNTER_TAG => ScalarValue::counter(as_i64()?),
+        TIMESTAMP_TAG => ScalarValue::Timestamp(as_i64()?),
+        BYTES_TAG => {
+            use base64::{engine::general_purpose, Engine as _};
+            let encoded = value.as_str().ok_or(AutomergeError::Fail)?;
+            let bytes = general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| AutomergeError::Fail)?;
+            ScalarValue::Bytes(bytes)
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// The fields `json` writes as a map: its own for a plain object, those of
+/// the wrapped map for a [`MAP_TAG`] object when `tagged`, and `None` for
+/// anything that is not stored as a map, including the scalar tags
+/// [`tagged_scalar`] recognizes. A [`MAP_TAG`] around a non-object fails.
+fn json_map_fields(
+    json: &JsonValue,
+    tagged: bool,
+) -> Result<Option<&serde_json::Map<String, JsonValue>>, AutomergeError> {
+    let fields = match json {
+        JsonValue::Object(fields) => fields,
+        _ => return Ok(None),
+    };
+    if !tagged {
+        return Ok(Some(fields));
+    }
+    match fields.get(MAP_TAG) {
+        Some(JsonValue::Object(inner)) if fields.len() == 1 => Ok(Some(inner)),
+        Some(_) if fields.len() == 1 => Err(AutomergeError::Fail),
+        _ if tagged_scalar(fields)?.is_some() => Ok(None),
+        _ => Ok(Some(fields)),
+    }
+}
+
+/// Gzip `saved` behind the header [`decompress_saved`] recognizes.
+fn compress_saved(saved: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut out = COMPRESSED_SAVE_MAGIC.to_vec();
+    out.push(CODEC_GZIP);
+    let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+    encoder
+        .write_all(saved)
+        .and_then(|_| encoder.finish())
+        .expect("gzip into a Vec cannot fail")
+}
+
+/// Strip and undo the header written by [`RedisAutomergeClient::save_compressed`].
+///
+/// Bytes without the header are returned as they are, so plain saves keep
+/// loading unchanged.
+fn decompress_saved(bytes: &[u8]) -> Result<std::borrow::Cow<'_, [u8]>, AutomergeError> {
+    use std::io::Read;
+
+    let invalid = |msg: String| {
+        AutomergeError::Deflate(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+    };
+    let rest = match bytes.strip_prefix(COMPRESSED_SAVE_MAGIC.as_slice()) {
+        Some(rest) => rest,
+        None => return Ok(std::borrow::Cow::Borrowed(bytes)),
+    };
+    match rest.split_first() {
+        Some((&CODEC_GZIP, payload)) => {
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(payload)
+                .take(MAX_DECOMPRESSED_BYTES as u64 + 1)
+                .read_to_end(&mut out)
+                .map_err(AutomergeError::Deflate)?;
+            if out.len() > MAX_DECOMPRESSED_BYTES {
+                return Err(invalid(format!(
+                    "decompressed document exceeds {} byte limit",
+                    MAX_DECOMPRESSED_BYTES
+                )));
+            }
+            Ok(std::borrow::Cow::Owned(out))
+        }
+        Some((codec, _)) => Err(invalid(format!("unknown compression codec {}", codec))),
+        None => Err(invalid("compressed save is missing its codec".to_string())),
+    }
+}
+
+/// Convenience methods for integrating Automerge with Redis persistence layers.
+///
+/// AOF replication is *not* handled here. Each AM.* write command in
+/// `lib.rs` calls `ctx.replicate(cmd, args)` via `finalize_write_meta`,
+/// which causes Redis to append the original command to the AOF file;
+/// on restart, the command is replayed by its normal handler. See
+/// audit #9 in `SECURITY_AUDIT.md` for the analysis of why a per-client
+/// change buffer was unnecessary.
+pub trait RedisAutomergeExt {
+    /// Load an Automerge document from its persisted binary form.
+    ///
+    /// This is typically used when restoring a document from Redis' RDB
+    /// persistence format. Output of
+    /// [`RedisAutomergeClient::save_compressed`] is detected by its header
+    /// and decompressed first.
+    fn load(bytes: &[u8]) -> Result<Self, AutomergeError>
+    where
+        Self: Sized;
+
+    /// Save the current state of the document to a compact binary
+    /// representation suitable for RDB persistence.
+    fn save(&self) -> Vec<u8>;
+
+    /// Apply a list of changes to the document.
+    fn apply(&mut self, changes: Vec<Change>) -> Result<(), AutomergeError>;
+}
+
+/// Client for managing an Automerge CRDT document with Redis-specific features.
+///
+/// This struct wraps an Automerge document and provides:
+/// - Path-based access to nested data structures (maps and lists)
+/// - Type-safe operations for common data types
+///
+/// AOF persistence is handled at the Redis command level (each AM.*
+/// write replicates its originating command via `ctx.replicate`); the
+/// client does not carry a per-instance change buffer.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use redis_automerge::ext::RedisAutomergeClient;
+///
+/// let mut client = RedisAutomergeClient::new();
+///
+/// // Work with nested maps
+/// client.put_text("config.host", "localhost").unwrap();
+/// client.put_int("config.port", 6379).unwrap();
+///
+/// // Work with lists
+/// client.create_list("tags").unwrap();
+/// client.append_text("tags", "redis").unwrap();
+/// client.append_text("tags", "crdt").unwrap();
+///
+/// // Retrieve values
+/// let host = client.get_text("config.host").unwrap();
+/// let tag = client.get_text("tags[0]").unwrap();
+/// ```
+pub struct RedisAutomergeClient {
+    doc: Automerge,
+    /// Whether write commands should publish structured patches for this
+    /// document (opted into via `AM.NEW <key> PATCHES`). Persisted
+    /// alongside the document bytes in RDB and re-emitted on AOF rewrite.
+    publish_patches: bool,
+    /// Largest saved size, in bytes, write commands may grow the document
+    /// to (set via `AM.NEW <key> MAXBYTES <n>`); 0 means no budget.
+    /// Persisted in RDB and re-emitted on AOF rewrite like `publish_patches`.
+    max_bytes: usize,
+    /// Heads and saved size at the last full save taken to check the
+    /// budget, from which [`budget_exceeded`](Self::budget_exceeded)
+    /// estimates the current size. `None` until the first check, and reset
+    /// whenever the history is rewritten.
+    measured_size: Option<(Vec<ChangeHash>, usize)>,
+}
+
+impl RedisAutomergeClient {
+    /// Creates a new client with an empty Automerge document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client = RedisAutomergeClient::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            doc: Automerge::new(),
+            publish_patches: false,
+            max_bytes: 0,
+            measured_size: None,
+        }
+    }
+
+    /// Creates a new client with an empty document that writes its changes
+    /// as `actor` instead of a random actor id.
+    ///
+    /// Two documents built with the same actor and the same edits produce
+    /// the same change hashes, which makes multi-client setups reproducible.
+    /// Each live copy of a document still needs its own actor: two copies
+    /// writing as the same actor will collide when merged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client = RedisAutomergeClient::with_actor(&[0xab, 0xcd]);
+    /// assert_eq!(client.actor_id(), vec![0xab, 0xcd]);
+    /// ```
+    pub fn with_actor(actor: &[u8]) -> Self {
+        Self {
+            doc: Automerge::new().with_actor(automerge::ActorId::from(actor)),
+            publish_patches: false,
+            max_bytes: 0,
+            measured_size: None,
+        }
+    }
+
+    /// Returns whether structured patches are published for this document.
+    pub fn publish_patches(&self) -> bool {
+        self.publish_patches
+    }
+
+    /// Enable or disable publishing of structured patches for this document.
+    pub fn set_publish_patches(&mut self, enabled: bool) {
+        self.publish_patches = enabled;
+    }
+
+    /// Returns the document's size budget in bytes, or 0 if it has none.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Set the document's size budget in bytes; 0 removes it.
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Returns the document's saved size if it is over its budget.
+    ///
+    /// The size is that of [`save`](RedisAutomergeExt::save), history
+    /// included. Saving on every write would cost as much as the document
+    /// is large, so between saves the size is estimated as the last measured
+    /// size plus the encoded size of every change since, which overstates
+    /// what those changes add to a save. Only an estimate over the budget
+    /// triggers a save to measure the size exactly. Documents without a
+    /// budget are never over it and cost nothing to check.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.set_max_bytes(200);
+    /// client.put_text("note", "short").unwrap();
+    /// assert_eq!(client.budget_exceeded(), None);
+    ///
+    /// // Deflate would shrink a repeated string, so vary it.
+    /// let essay: String = (0..100u32).map(|i| format!("{:x}", i * 7919)).collect();
+    /// client.put_text("essay", &essay).unwrap();
+    /// assert!(client.budget_exceeded().is_some());
+    /// ```
+    pub fn budget_exceeded(&mut self) -> Option<usize> {
+        if self.max_bytes == 0 {
+            return None;
+        }
+        if let Some((heads, size)) = &self.measured_size {
+            let added: usize = self
+                .doc
+                .get_changes(heads)
+                .iter()
+                .map(|c| c.raw_bytes().len())
+                .sum();
+            if size + added <= self.max_bytes {
+                return None;
+            }
+        }
+        let size = self.doc.save().len();
+        self.measured_size = Some((self.doc.get_heads(), size));
+        (size > self.max_bytes).then_some(size)
+    }
+
+    /// Discard every change not reachable from `heads`, returning the
+    /// document to the state it had at those heads.
+    ///
+    /// Used to undo a write that has already been committed but not yet
+    /// published or replicated, such as one that broke the size budget.
+    /// The document keeps writing as the same actor, so the next change
+    /// reuses the sequence number of the discarded one. That is only safe
+    /// because no peer has seen the discarded changes. Changes held back
+    /// for missing dependencies stay queued.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hash in `heads` is not in the document.
+    pub fn roll_back_to(&mut self, heads: &[ChangeHash]) -> Result<(), AutomergeError> {
+        let queued = self.queued_changes();
+        self.restore(heads, &queued)
+    }
+
+    /// Changes held back for missing dependencies, encoded back to back.
+    fn queued_changes(&self) -> Vec<u8> {
+        // A save appends the queued changes after the document, so the tail
+        // past a save without them is exactly the queue.
+        if self.doc.get_missing_deps(&[]).is_empty() {
+            return Vec::new();
+        }
+        let with_queue = self.doc.save();
+        let without_queue = self.doc.save_with_options(automerge::SaveOptions {
+            retain_orphans: false,
+            ..automerge::SaveOptions::default()
+        });
+        with_queue[without_queue.len()..].to_vec()
+    }
+
+    /// Reset the document to `heads` with `queued` (from
+    /// [`queued_changes`](Self::queued_changes)) as its queue.
+    fn restore(&mut self, heads: &[ChangeHash], queued: &[u8]) -> Result<(), AutomergeError> {
+        // A fork keeps only applied changes, so the queue is loaded back in.
+        let actor = self.doc.get_actor().clone();
+        let mut doc = self.doc.fork_at(heads)?.with_actor(actor);
+        if !queued.is_empty() {
+            doc.load_incremental(queued)?;
+        }
+        self.doc = doc;
+        self.measured_size = None;
+        Ok(())
+    }
+
+    /// Returns the raw bytes of the actor id this document writes changes as.
+    pub fn actor_id(&self) -> Vec<u8> {
+        self.doc.get_actor().to_bytes().to_vec()
+    }
+
+    /// Write future changes as the actor `actor`, continuing its sequence
+    /// numbers if the document already holds changes from it.
+    ///
+    /// [`load`](RedisAutomergeExt::load) gives every document a fresh actor;
+    /// RDB loading and `AM.LOAD ... ACTOR` use this to restore the actor the
+    /// document had when it was saved.
+    pub fn set_actor_id(&mut self, actor: &[u8]) {
+        self.doc.set_actor(automerge::ActorId::from(actor));
+    }
 
     /// Estimate the in-memory footprint of this client in bytes.
     ///
@@ -533,6 +1965,68 @@ impl RedisAutomergeClient {
         self.put_text_with_change(path, value).map(|_| ())
     }
 
+    /// Retrieves the bytes of the scalar at the specified path.
+    ///
+    /// A bytes value is returned as stored and a string or text object as
+    /// its UTF-8 encoding. Other scalars are returned as the UTF-8 of their
+    /// plain text form: decimal numbers, `true` or `false`, and
+    /// milliseconds since the epoch for timestamps. Returns `None` if the
+    /// path doesn't exist or holds null.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the field
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.put_int("user.age", 30).unwrap();
+    ///
+    /// assert_eq!(client.get_raw("user.name").unwrap(), Some(b"Alice".to_vec()));
+    /// assert_eq!(client.get_raw("user.age").unwrap(), Some(b"30".to_vec()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is malformed or the value is a map or
+    /// list.
+    pub fn get_raw(&self, path: &str) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let (last, parent_path) = match segments.split_last() {
+            Some(split) => split,
+            None => return Err(AutomergeError::Fail),
+        };
+        let parent_obj = match navigate_path_read(&self.doc, parent_path)? {
+            Some(obj) => obj,
+            None => return Ok(None),
+        };
+
+        let scalar = match get_value_from_parent(&self.doc, &parent_obj, last)? {
+            Some((Value::Scalar(s), _)) => s.into_owned(),
+            Some((Value::Object(automerge::ObjType::Text), obj_id)) => {
+                return Ok(Some(self.doc.text(&obj_id)?.into_bytes()));
+            }
+            Some((Value::Object(_), _)) => return Err(AutomergeError::Fail),
+            None => return Ok(None),
+        };
+        let bytes = match scalar {
+            ScalarValue::Bytes(b) => b,
+            ScalarValue::Str(s) => s.as_bytes().to_vec(),
+            ScalarValue::Int(i) | ScalarValue::Timestamp(i) => i.to_string().into_bytes(),
+            ScalarValue::Uint(u) => u.to_string().into_bytes(),
+            ScalarValue::F64(f) => f.to_string().into_bytes(),
+            ScalarValue::Counter(c) => i64::from(&c).to_string().into_bytes(),
+            ScalarValue::Boolean(b) => b.to_string().into_bytes(),
+            ScalarValue::Unknown { bytes, .. } => bytes,
+            ScalarValue::Null => return Ok(None),
+        };
+        Ok(Some(bytes))
+    }
+
     /// Retrieves a text value from the specified path.
     ///
     /// Returns `None` if the path doesn't exist or the value is not text.
@@ -588,9 +2082,83 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Apply raw Automerge change bytes to this document.
+    /// Returns the length of the string at `path`, in Unicode scalar values.
     ///
-    /// This allows applying changes generated by one document to another,
+    /// Works for both scalar strings and Text objects, mirroring what
+    /// [`get_text`](Self::get_text) can read, without materialising the
+    /// string. Returns `None` if the path is absent or holds something other
+    /// than text, like Redis `STRLEN` does for missing keys.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("greeting", "héllo").unwrap();
+    ///
+    /// assert_eq!(client.text_len("greeting").unwrap(), Some(5));
+    /// assert_eq!(client.text_len("missing").unwrap(), None);
+    /// ```
+    pub fn text_len(&self, path: &str) -> Result<Option<usize>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        match get_value_from_parent(&self.doc, &parent_obj, &field_name[0])? {
+            Some((Value::Scalar(s), _)) => {
+                if let ScalarValue::Str(t) = s.as_ref() {
+                    return Ok(Some(t.chars().count()));
+                }
+            }
+            Some((Value::Object(automerge::ObjType::Text), obj_id)) => {
+                return Ok(Some(self.doc.length(&obj_id)));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Returns the length of the string at `path` counted in `unit`.
+    ///
+    /// Like [`text_len`](Self::text_len), which counts Unicode scalar
+    /// values, but lets clients get lengths in the unit their own strings
+    /// use. Returns `None` if the path is absent or not text.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, TextUnit};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("greeting", "hi 👋").unwrap();
+    ///
+    /// assert_eq!(client.text_len_in("greeting", TextUnit::CodePoints).unwrap(), Some(4));
+    /// assert_eq!(client.text_len_in("greeting", TextUnit::Utf16).unwrap(), Some(5));
+    /// assert_eq!(client.text_len_in("greeting", TextUnit::Bytes).unwrap(), Some(7));
+    /// ```
+    pub fn text_len_in(&self, path: &str, unit: TextUnit) -> Result<Option<usize>, AutomergeError> {
+        if unit == TextUnit::CodePoints {
+            return self.text_len(path);
+        }
+        Ok(self.get_text(path)?.map(|text| unit.len(&text)))
+    }
+
+    /// Apply raw Automerge change bytes to this document.
+    ///
+    /// This allows applying changes generated by one document to another,
     /// enabling real-time synchronization between clients.
     ///
     /// # Arguments
@@ -616,20 +2184,2156 @@ impl RedisAutomergeClient {
         Ok(())
     }
 
-    /// Insert a text value and return the raw change bytes.
+    /// Checks that the document survives a save/reload round trip.
+    ///
+    /// The document is serialized exactly as it would be for RDB
+    /// persistence, loaded back into a scratch document, and the heads of
+    /// the two are compared. The stored document is only read, never
+    /// modified. A healthy document always passes; a failure means the
+    /// in-memory state can no longer be persisted faithfully and would
+    /// surface as lost history (or a failed load) after the next restart.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("field", "value").unwrap();
+    /// assert!(client.verify().is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VerifyError::Reload`] if the saved bytes do not load, or
+    /// [`VerifyError::HeadsMismatch`] if the reloaded heads differ.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        let bytes = self.doc.save();
+        let reloaded = Automerge::load(&bytes).map_err(VerifyError::Reload)?;
+        let expected = self.doc.get_heads();
+        let actual = reloaded.get_heads();
+        if expected != actual {
+            return Err(VerifyError::HeadsMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Export the document as a self-describing `AM.DUMP` blob.
+    ///
+    /// Unlike [`save`](RedisAutomergeExt::save), which is Automerge's own
+    /// compressed document format, a dump carries a header and the full
+    /// change history as individually framed changes. Individual change
+    /// encoding is the most stable part of the Automerge format, so a dump
+    /// is the safer way to move a document between servers or versions.
+    /// All integers are big-endian:
+    ///
+    /// ```text
+    /// magic          6 bytes   "AMDUMP"
+    /// format         u8        DUMP_FORMAT_VERSION
+    /// flags          u8        bit 0: publish structured patches
+    /// module version u16 len + UTF-8 crate version that wrote the dump
+    /// actor id       u16 len + bytes
+    /// change count   u32
+    /// changes        count x (u32 len + raw change bytes), causal order
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("name", "Alice").unwrap();
+    ///
+    /// let blob = client.dump();
+    /// let copy = RedisAutomergeClient::import(&blob).unwrap();
+    /// assert_eq!(copy.get_text("name").unwrap(), Some("Alice".to_string()));
+    /// ```
+    pub fn dump(&self) -> Vec<u8> {
+        let version = env!("CARGO_PKG_VERSION").as_bytes();
+        let actor = self.doc.get_actor().to_bytes();
+        let changes = self.doc.get_changes(&[]);
+
+        let mut out = Vec::new();
+        out.extend_from_slice(DUMP_MAGIC);
+        out.push(DUMP_FORMAT_VERSION);
+        out.push(u8::from(self.publish_patches));
+        out.extend_from_slice(&(version.len() as u16).to_be_bytes());
+        out.extend_from_slice(version);
+        out.extend_from_slice(&(actor.len() as u16).to_be_bytes());
+        out.extend_from_slice(actor);
+        out.extend_from_slice(&(changes.len() as u32).to_be_bytes());
+        out.extend_from_slice(&pack_changes(&changes));
+        out
+    }
+
+    /// Rebuild a document from a [`dump`](Self::dump) blob.
+    ///
+    /// The header is validated before any change is decoded. The recorded
+    /// actor id is informational only: the imported document gets a fresh
+    /// actor, as `AM.LOAD` does, so that the source and the copy never
+    /// write changes under the same actor and sequence number.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DumpError::BadMagic`] or [`DumpError::UnsupportedVersion`]
+    /// for a foreign or newer blob, [`DumpError::Malformed`] if the framing
+    /// is inconsistent or the history references changes the blob does not
+    /// contain, and [`DumpError::Automerge`] if a change does not decode.
+    pub fn import(blob: &[u8]) -> Result<Self, DumpError> {
+        if !blob.starts_with(DUMP_MAGIC) {
+            return Err(DumpError::BadMagic);
+        }
+        let mut buf = &blob[DUMP_MAGIC.len()..];
+        let version = take_bytes(&mut buf, 1, "header")?[0];
+        if version != DUMP_FORMAT_VERSION {
+            return Err(DumpError::UnsupportedVersion(version));
+        }
+        let flags = take_bytes(&mut buf, 1, "header")?[0];
+
+        let read_u16 = |buf: &mut &[u8]| -> Result<usize, DumpError> {
+            let b = take_bytes(buf, 2, "header")?;
+            Ok(u16::from_be_bytes([b[0], b[1]]) as usize)
+        };
+        let len = read_u16(&mut buf)?;
+        let module_version = take_bytes(&mut buf, len, "module version")?;
+        if std::str::from_utf8(module_version).is_err() {
+            return Err(DumpError::Malformed("module version is not UTF-8"));
+        }
+        let len = read_u16(&mut buf)?;
+        take_bytes(&mut buf, len, "actor id")?;
+
+        let count = take_bytes(&mut buf, 4, "change count")?;
+        let count = u32::from_be_bytes([count[0], count[1], count[2], count[3]]);
+        let mut changes = Vec::new();
+        for _ in 0..count {
+            changes.push(take_framed_change(&mut buf)?);
+        }
+        if !buf.is_empty() {
+            return Err(DumpError::Malformed("trailing bytes after last change"));
+        }
+
+        let mut doc = Automerge::new();
+        doc.apply_changes(changes)?;
+        if !doc.get_missing_deps(&[]).is_empty() {
+            return Err(DumpError::Malformed("change history is incomplete"));
+        }
+        Ok(Self {
+            doc,
+            publish_patches: flags & 1 != 0,
+            max_bytes: 0,
+            measured_size: None,
+        })
+    }
+
+    /// Save the document gzip-compressed, behind a small header naming the
+    /// codec.
+    ///
+    /// Automerge's own format already compresses large columns, so the
+    /// saving over [`save`](RedisAutomergeExt::save) comes mostly from change
+    /// metadata and is modest; very small documents can even grow by the
+    /// gzip framing. The result is accepted by
+    /// [`load`](RedisAutomergeExt::load), which detects the header.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, RedisAutomergeExt};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("name", "Alice").unwrap();
+    ///
+    /// let compressed = client.save_compressed();
+    /// let loaded = RedisAutomergeClient::load(&compressed).unwrap();
+    /// assert_eq!(loaded.get_text("name").unwrap(), Some("Alice".to_string()));
+    /// ```
+    pub fn save_compressed(&self) -> Vec<u8> {
+        compress_saved(&self.doc.save())
+    }
+
+    /// Save the document's current state in a canonical form, so that two
+    /// documents showing the same values save to the same bytes whatever
+    /// histories led there.
+    ///
+    /// The visible state is copied, in key and index order, into a fresh
+    /// document as one change by [`CANONICAL_ACTOR`] with a zero timestamp,
+    /// the way [`truncate_history`](Self::truncate_history) builds it. The
+    /// result loads like any save, but it shares no history with this
+    /// document, so it is a fingerprint or cache entry rather than
+    /// something to sync from. Only the winning value of a conflict is
+    /// kept, and counters keep their current total. With `compress` the
+    /// output is gzipped as by [`save_compressed`](Self::save_compressed),
+    /// which is deterministic too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut a = RedisAutomergeClient::new();
+    /// a.put_text("name", "Alice").unwrap();
+    /// a.put_int("age", 30).unwrap();
+    ///
+    /// let mut b = RedisAutomergeClient::new();
+    /// b.put_int("age", 29).unwrap();
+    /// b.put_text("name", "Alice").unwrap();
+    /// b.put_int("age", 30).unwrap();
+    ///
+    /// assert_eq!(a.save_canonical(false).unwrap(), b.save_canonical(false).unwrap());
+    /// ```
+    pub fn save_canonical(&self, compress: bool) -> Result<Vec<u8>, AutomergeError> {
+        let mut doc = Automerge::new().with_actor(automerge::ActorId::from(CANONICAL_ACTOR));
+        let mut tx = doc.transaction();
+        copy_object_contents(Some(&self.doc), &ROOT, &mut tx, &ROOT)?;
+        tx.commit_with(automerge::transaction::CommitOptions::default().with_time(0));
+        let saved = doc.save();
+        Ok(if compress {
+            compress_saved(&saved)
+        } else {
+            saved
+        })
+    }
+
+    /// Replace the document with a fresh one holding only its current state.
+    ///
+    /// All change history is discarded: the new document is built from the
+    /// visible values in a single change, so overwritten values, deleted
+    /// text and losing conflict values can no longer be recovered through
+    /// `get_changes`, `save` or `dump`. This is intended for erasure
+    /// requests. The rewritten document shares no history with any other
+    /// copy, so changes exchanged with replicas or clients holding the old
+    /// history will no longer merge meaningfully.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("email", "old@example.com").unwrap();
+    /// client.put_text("email", "new@example.com").unwrap();
+    /// client.truncate_history().unwrap();
+    ///
+    /// assert_eq!(client.get_changes(&[]).len(), 1);
+    /// assert_eq!(client.get_text("email").unwrap(), Some("new@example.com".to_string()));
+    /// ```
+    pub fn truncate_history(&mut self) -> Result<(), AutomergeError> {
+        self.doc = self.state_only_doc()?;
+        self.measured_size = None;
+        Ok(())
+    }
+
+    /// Copy the document's current state into a new, unrelated document.
+    ///
+    /// The copy holds the same values in a single change, built as
+    /// [`truncate_history`](Self::truncate_history) builds it, and shares
+    /// no history with this one. It writes as a fresh actor and does not
+    /// inherit the `PATCHES` setting. Use it to turn a live document into
+    /// a clean template.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("title", "Draft").unwrap();
+    /// client.put_text("title", "Final").unwrap();
+    ///
+    /// let template = client.snapshot().unwrap();
+    /// assert_eq!(template.get_changes(&[]).len(), 1);
+    /// assert_eq!(template.get_text("title").unwrap(), Some("Final".to_string()));
+    /// ```
+    pub fn snapshot(&self) -> Result<Self, AutomergeError> {
+        Ok(Self {
+            doc: self.state_only_doc()?,
+            publish_patches: false,
+            max_bytes: 0,
+            measured_size: None,
+        })
+    }
+
+    /// A new document holding this one's current state with `overrides`
+    /// deep-merged on top, for stamping out per-tenant copies of a template.
+    ///
+    /// The state is copied as [`snapshot`](Self::snapshot) copies it, and
+    /// `overrides`, a parsed JSON object, is merged into the root as
+    /// [`put_json`](Self::put_json) merges: nested objects merge key by key,
+    /// other values replace the template's, and keys the overrides leave
+    /// out keep the template's value. Copy and merge are a single change
+    /// under a fresh actor. This document is not modified.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut template = RedisAutomergeClient::new();
+    /// template.put_json("", r#"{"plan":"free","limits":{"seats":5,"storage":10}}"#, false).unwrap();
+    ///
+    /// let overrides = serde_json::json!({"limits": {"seats": 50}});
+    /// let tenant = template.instantiate(&overrides).unwrap();
+    /// assert_eq!(tenant.get_int("limits.seats").unwrap(), Some(50));
+    /// assert_eq!(tenant.get_int("limits.storage").unwrap(), Some(10));
+    /// assert_eq!(tenant.get_changes(&[]).len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `overrides` is not a JSON object or nests deeper
+    /// than [`MAX_JSON_DEPTH`].
+    pub fn instantiate(&self, overrides: &JsonValue) -> Result<Self, AutomergeError> {
+        let mut instance = RedisAutomergeClient::new();
+        let mut tx = instance.doc.transaction();
+        copy_object_contents(Some(&self.doc), &ROOT, &mut tx, &ROOT)?;
+        write_json_at(&mut tx, &[], overrides, true, false)?;
+        let (_hash, _patch) = tx.commit();
+        Ok(instance)
+    }
+
+    /// A fresh document holding this one's visible state in one change.
+    fn state_only_doc(&self) -> Result<Automerge, AutomergeError> {
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        copy_object_contents(Some(&self.doc), &ROOT, &mut tx, &ROOT)?;
+        let (_hash, _patch) = tx.commit();
+        Ok(doc)
+    }
+
+    /// Estimate how much of the document's saved size is history.
+    ///
+    /// The current state is copied into a scratch document, built in a
+    /// single change the same way [`truncate_history`](Self::truncate_history)
+    /// does, and its save size is compared with the full save. The document
+    /// itself is not modified. Both saves are computed, so this costs about
+    /// as much as two `save()` calls.
+    ///
+    /// `history_bytes` is an estimate: Automerge compresses columns, so the
+    /// two sizes do not split exactly along state and history, and it never
+    /// goes below zero even when the snapshot happens to encode larger.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// for i in 0..100 {
+    ///     client.put_int("n", i).unwrap();
+    /// }
+    ///
+    /// let before = client.mem_breakdown().unwrap();
+    /// client.truncate_history().unwrap();
+    /// let after = client.mem_breakdown().unwrap();
+    /// assert!(after.history_bytes < before.history_bytes);
+    /// ```
+    pub fn mem_breakdown(&self) -> Result<MemBreakdown, AutomergeError> {
+        let total_bytes = self.doc.save().len();
+        let state_bytes = self.state_only_doc()?.save().len();
+        let stats = self.doc.stats();
+        Ok(MemBreakdown {
+            total_bytes,
+            state_bytes,
+            history_bytes: total_bytes.saturating_sub(state_bytes),
+            ops: stats.num_ops as usize,
+            changes: stats.num_changes as usize,
+            actors: stats.num_actors as usize,
+        })
+    }
+
+    /// Save the map at `path` as a standalone document.
+    ///
+    /// The map's current contents become the root of a fresh document,
+    /// built in a single change, and the reply is that document's `save()`
+    /// bytes, ready for `load` or `AM.LOAD`. The export is a snapshot: none
+    /// of the subtree's history comes with it, conflicting values lose to
+    /// the winner, and counters are re-seeded from their current totals.
+    /// The new document shares no history with this one, so changes made to
+    /// either copy will not merge back into the other.
+    ///
+    /// Returns `None` if nothing is stored at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, RedisAutomergeExt};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("orders.o1.status", "shipped").unwrap();
+    ///
+    /// let bytes = client.export_subtree("orders.o1").unwrap().unwrap();
+    /// let order = RedisAutomergeClient::load(&bytes).unwrap();
+    /// assert_eq!(order.get_text("status").unwrap(), Some("shipped".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is malformed or the value there is not
+    /// a map, since only a map can become a document root.
+    pub fn export_subtree(&self, path: &str) -> Result<Option<Vec<u8>>, AutomergeError> {
+        Ok(self.fork_subtree(path)?.map(|fork| fork.doc.save()))
+    }
+
+    /// A new, independent document whose root is the map at `path`.
+    ///
+    /// This is [`export_subtree`](Self::export_subtree) without the save
+    /// round trip: the map's current contents are copied in a single change
+    /// under a fresh actor, and nothing outside the subtree, nor any of its
+    /// history, is carried over. The fork can be edited and synced like any
+    /// other document, but it shares no history with this one.
+    ///
+    /// Returns `None` if nothing is stored at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("public.title", "Roadmap").unwrap();
+    /// client.put_text("private.notes", "internal").unwrap();
+    ///
+    /// let fork = client.fork_subtree("public").unwrap().unwrap();
+    /// assert_eq!(fork.get_text("title").unwrap(), Some("Roadmap".to_string()));
+    /// assert_eq!(fork.get_text("private.notes").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is malformed or the value there is not
+    /// a map.
+    pub fn fork_subtree(&self, path: &str) -> Result<Option<Self>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let src = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => {
+                    return match self.get_typed_value(path)? {
+                        Some(_) => Err(AutomergeError::Fail),
+                        None => Ok(None),
+                    }
+                }
+            }
+        };
+        if !matches!(
+            self.doc.object_type(&src)?,
+            automerge::ObjType::Map | automerge::ObjType::Table
+        ) {
+            return Err(AutomergeError::Fail);
+        }
+        let mut fork = RedisAutomergeClient::new();
+        let mut tx = fork.doc.transaction();
+        copy_object_contents(Some(&self.doc), &src, &mut tx, &ROOT)?;
+        let (_hash, _patch) = tx.commit();
+        Ok(Some(fork))
+    }
+
+    /// The `n` entries with the largest estimated serialized size, largest
+    /// first, as `(path, bytes)` pairs.
+    ///
+    /// An entry's size is the size of its current content: the bytes of
+    /// strings, text and byte values, 8 bytes for numbers, 1 for booleans
+    /// and nulls, and for maps and lists the total of their entries plus
+    /// the length of each map key. It leaves out history and Automerge's
+    /// own encoding overhead, so sizes are for comparing entries with each
+    /// other. Only the root's entries are ranked unless `recursive` is set,
+    /// in which case every map and list entry at any depth is ranked too,
+    /// with paths in the form [`paths`](Self::paths) returns. A map key
+    /// containing `.`, `[` or `]` is listed as it is, even though such a
+    /// path cannot be passed back to the path commands. Nothing in the
+    /// document is modified.
+    ///
+    /// Sizes are summed bottom-up in one walk of the document, so each
+    /// value is visited once whatever its depth.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("title", "Notes").unwrap();
+    /// client.put_json("log", &format!("{:?}", vec!["entry"; 500]), false).unwrap();
+    ///
+    /// let heaviest = client.heavy_paths(1, false).unwrap();
+    /// assert_eq!(heaviest[0].0, "log");
+    /// ```
+    pub fn heavy_paths(
+        &self,
+        n: usize,
+        recursive: bool,
+    ) -> Result<Vec<(String, usize)>, AutomergeError> {
+        let mut sizes = Vec::new();
+        self.content_size(&ROOT, "", recursive, &mut sizes)?;
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes.truncate(n);
+        Ok(sizes)
+    }
+
+    /// Estimated size of the contents of `obj`, for
+    /// [`heavy_paths`](Self::heavy_paths). Pushes `(path, size)` for each of
+    /// `obj`'s entries onto `out`, and for nested entries too when
+    /// `recursive` is set.
+    fn content_size(
+        &self,
+        obj: &ObjId,
+        base: &str,
+        recursive: bool,
+        out: &mut Vec<(String, usize)>,
+    ) -> Result<usize, AutomergeError> {
+        let entry = |path: String, value: Value, id: ObjId, out: &mut Vec<_>| {
+            let size = match value {
+                Value::Scalar(s) => scalar_size(&s),
+                Value::Object(automerge::ObjType::Text) => self.doc.text(&id)?.len(),
+                Value::Object(_) if recursive => self.content_size(&id, &path, true, out)?,
+                Value::Object(_) => self.content_size(&id, &path, false, &mut Vec::new())?,
+            };
+            out.push((path, size));
+            Ok::<_, AutomergeError>(size)
+        };
+        let mut total = 0;
+        match self.doc.object_type(obj)? {
+            automerge::ObjType::Map | automerge::ObjType::Table => {
+                for key in self.doc.keys(obj) {
+                    if let Some((value, id)) = self.doc.get(obj, key.as_str())? {
+                        let path = if base.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{}.{}", base, key)
+                        };
+                        total += key.len() + entry(path, value, id, out)?;
+                    }
+                }
+            }
+            automerge::ObjType::List => {
+                for i in 0..self.doc.length(obj) {
+                    if let Some((value, id)) = self.doc.get(obj, i)? {
+                        total += entry(format!("{}[{}]", base, i), value, id, out)?;
+                    }
+                }
+            }
+            automerge::ObjType::Text => total = self.doc.text(obj)?.len(),
+        }
+        Ok(total)
+    }
+
+    /// Apply `changes`, skipping any the document already contains.
+    ///
+    /// Returns the positions (within `changes`) of the changes that were
+    /// genuinely new. A change counts as already present if the document
+    /// has applied a change with the same hash, or if it repeats an earlier
+    /// entry in the same batch. Applying a change twice is harmless for the
+    /// CRDT itself; callers use the returned positions so that republishing
+    /// and replicating reflect only new history.
+    ///
+    /// Changes whose dependencies are still missing are held back by
+    /// Automerge rather than applied. They are reported as new each time
+    /// they are offered until their dependencies arrive.
+    ///
+    /// The batch is all-or-nothing. It is applied in place, and if any
+    /// change is rejected (for example, it reuses a sequence number its
+    /// actor already spent on a different change) the document is rolled
+    /// back to exactly how it was, with none of the other changes applied
+    /// or held back for later. A document that already has changes held
+    /// back is saved once before the batch so that queue can be restored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use automerge::Change;
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut source = RedisAutomergeClient::new();
+    /// let bytes = source.put_text_with_change("field", "value").unwrap().unwrap();
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// let change = Change::from_bytes(bytes.clone()).unwrap();
+    /// assert_eq!(client.apply_new_changes(vec![change]).unwrap(), vec![0]);
+    ///
+    /// let change = Change::from_bytes(bytes).unwrap();
+    /// assert!(client.apply_new_changes(vec![change]).unwrap().is_empty());
+    /// ```
+    pub fn apply_new_changes(
+        &mut self,
+        changes: Vec<Change>,
+    ) -> Result<Vec<usize>, AutomergeError> {
+        let mut seen = std::collections::HashSet::new();
+        let mut new_positions = Vec::new();
+        let mut new_changes = Vec::new();
+        for (i, change) in changes.into_iter().enumerate() {
+            let hash = change.hash();
+            if self.doc.get_change_by_hash(&hash).is_some() || !seen.insert(hash) {
+                continue;
+            }
+            new_positions.push(i);
+            new_changes.push(change);
+        }
+        if !new_changes.is_empty() {
+            // Automerge keeps what it applied of a failed batch, and queues
+            // the rest as if it were waiting on dependencies, so put back
+            // both the heads and the queue from before the batch.
+            let heads_before = self.doc.get_heads();
+            let queued_before = self.queued_changes();
+            if let Err(e) = self.doc.apply_changes(new_changes) {
+                self.restore(&heads_before, &queued_before)?;
+                return Err(e);
+            }
+        }
+        Ok(new_positions)
+    }
+
+    /// Merge a saved document into this one, as if receiving a peer's full
+    /// state.
+    ///
+    /// `bytes` may be anything [`RedisAutomergeExt::load`] accepts: the
+    /// output of [`RedisAutomergeExt::save`] or [`Self::save_compressed`],
+    /// or several encoded changes concatenated. Changes this document
+    /// already has are skipped.
+    ///
+    /// # Returns
+    ///
+    /// The raw bytes of each change that was new to this document, in the
+    /// order they were applied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, RedisAutomergeExt};
+    ///
+    /// let mut peer = RedisAutomergeClient::new();
+    /// peer.put_text("name", "Alice").unwrap();
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// let merged = client.merge_saved(&peer.save()).unwrap();
+    /// assert_eq!(merged.len(), 1);
+    /// assert_eq!(client.get_text("name").unwrap(), Some("Alice".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` cannot be loaded. The document is left
+    /// unchanged in that case.
+    pub fn merge_saved(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, AutomergeError> {
+        let changes = self.new_saved_changes(bytes)?;
+        let new_positions = self.apply_new_changes(changes.clone())?;
+        Ok(new_positions
+            .into_iter()
+            .map(|i| changes[i].raw_bytes().to_vec())
+            .collect())
+    }
+
+    /// The changes in the saved document `bytes` that this document does
+    /// not have yet, in the order [`merge_saved`](Self::merge_saved) would
+    /// apply them, so callers can vet them before merging.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` cannot be loaded.
+    pub fn new_saved_changes(&self, bytes: &[u8]) -> Result<Vec<Change>, AutomergeError> {
+        let changes = Automerge::load(&decompress_saved(bytes)?)?.get_changes(&[]);
+        Ok(changes
+            .into_iter()
+            .filter(|c| self.doc.get_change_by_hash(&c.hash()).is_none())
+            .collect())
+    }
+
+    /// Insert a text value and return the raw change bytes.
+    ///
+    /// Like `put_text()` but returns Automerge change bytes that can
+    /// be published to other clients for real-time synchronization.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the field
+    /// * `value` - Text value to insert
+    ///
+    /// # Returns
+    ///
+    /// - `Some(Vec<u8>)` - Raw change bytes if a change was generated
+    /// - `None` - If no change was needed
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// let change = client.put_text_with_change("field", "hello").unwrap();
+    ///
+    /// if let Some(change_bytes) = change {
+    ///     // Publish to other clients
+    /// }
+    /// ```
+    pub fn put_text_with_change(
+        &mut self,
+        path: &str,
+        value: &str,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let mut tx = self.doc.transaction();
+
+        if segments.is_empty() {
+            return Err(AutomergeError::Fail);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+
+        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Insert a text value that expires at `expires_at_ms`.
+    ///
+    /// The deadline is stored as a timestamp in a sibling key named after the
+    /// field plus [`EXPIRES_SUFFIX`], written in the same change as the
+    /// value. Nothing is removed automatically here: [`Self::is_expired`]
+    /// reports whether the deadline has passed and [`Self::reap_expired`]
+    /// deletes expired fields. Overwriting the field with `put_text` keeps
+    /// the deadline; delete the companion key to make it permanent.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the field; it must end in a map key
+    /// * `value` - Text value to store
+    /// * `expires_at_ms` - Deadline in milliseconds since the Unix epoch
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text_expiring("presence.alice", "online", 1_000).unwrap();
+    ///
+    /// assert!(!client.is_expired("presence.alice", 999).unwrap());
+    /// assert_eq!(client.reap_expired(1_000).unwrap(), 1);
+    /// assert_eq!(client.get_text("presence.alice").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is empty or ends in a list index.
+    pub fn put_text_expiring(
+        &mut self,
+        path: &str,
+        value: &str,
+        expires_at_ms: i64,
+    ) -> Result<(), AutomergeError> {
+        self.put_text_expiring_with_change(path, value, expires_at_ms)
+            .map(|_| ())
+    }
+
+    /// Insert an expiring text value and return the raw change bytes.
+    pub fn put_text_expiring_with_change(
+        &mut self,
+        path: &str,
+        value: &str,
+        expires_at_ms: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let (parent_path, field) = match segments.split_last() {
+            Some((PathSegment::Key(field), parent_path)) => (parent_path, field),
+            _ => return Err(AutomergeError::Fail),
+        };
+
+        let mut tx = self.doc.transaction();
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+        tx.put(&parent_obj, field.as_str(), value)?;
+        tx.put(
+            &parent_obj,
+            format!("{}{}", field, EXPIRES_SUFFIX),
+            ScalarValue::Timestamp(expires_at_ms),
+        )?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Whether the field at `path` has an expiry deadline at or before
+    /// `now_ms`. Fields without a deadline never expire.
+    pub fn is_expired(&self, path: &str, now_ms: i64) -> Result<bool, AutomergeError> {
+        let segments = parse_path(path)?;
+        let (parent_path, field) = match segments.split_last() {
+            Some((PathSegment::Key(field), parent_path)) => (parent_path, field),
+            _ => return Ok(false),
+        };
+        let parent_obj = match navigate_path_read(&self.doc, parent_path)? {
+            Some(obj) => obj,
+            None => return Ok(false),
+        };
+        if self.doc.object_type(&parent_obj)? != automerge::ObjType::Map {
+            return Ok(false);
+        }
+        deadline_passed(&self.doc, &parent_obj, field, now_ms)
+    }
+
+    /// Whether any field in the document carries an expiry deadline.
+    pub fn has_expiring_fields(&self) -> Result<bool, AutomergeError> {
+        let mut found = Vec::new();
+        collect_expired(&self.doc, &ROOT, "", i64::MAX, &mut found)?;
+        Ok(!found.is_empty())
+    }
+
+    /// Delete every field whose expiry deadline is at or before `now_ms`,
+    /// together with its deadline, in a single change.
+    ///
+    /// # Returns
+    ///
+    /// The number of expired fields removed.
+    pub fn reap_expired(&mut self, now_ms: i64) -> Result<usize, AutomergeError> {
+        // Each field is reaped together with its deadline.
+        self.reap_expired_with_change(now_ms)
+            .map(|(reaped, _)| reaped.len() / 2)
+    }
+
+    /// Delete expired fields and return the paths removed along with the
+    /// raw change bytes (`None` if nothing had expired).
+    ///
+    /// Each expired field contributes its own path followed by the path of
+    /// its deadline, so `AM.DEL` of the returned paths repeats the reap.
+    pub fn reap_expired_with_change(
+        &mut self,
+        now_ms: i64,
+    ) -> Result<(Vec<String>, Option<Vec<u8>>), AutomergeError> {
+        let mut expired = Vec::new();
+        collect_expired(&self.doc, &ROOT, "", now_ms, &mut expired)?;
+        if expired.is_empty() {
+            return Ok((Vec::new(), None));
+        }
+
+        let mut tx = self.doc.transaction();
+        let mut reaped = Vec::with_capacity(expired.len() * 2);
+        for (parent_obj, field, path) in expired {
+            if tx.get(&parent_obj, field.as_str())?.is_some() {
+                tx.delete(&parent_obj, field.as_str())?;
+            }
+            tx.delete(&parent_obj, format!("{}{}", field, EXPIRES_SUFFIX))?;
+            let companion = format!("{}{}", path, EXPIRES_SUFFIX);
+            reaped.push(path);
+            reaped.push(companion);
+        }
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok((reaped, Some(change.raw_bytes().to_vec())));
+            }
+        }
+
+        Ok((reaped, None))
+    }
+
+    /// Insert an integer value using a path (e.g., "user.age", "users[0].age", or "$.user.age").
+    /// Creates intermediate maps as needed. Array indices must already exist.
+    pub fn put_int(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.put_int_with_change(path, value).map(|_| ())
+    }
+
+    /// Retrieve an integer value using a path (e.g., "user.age", "users[0].age", or "$.user.age").
+    ///
+    /// A counter reads as its current total, for callers that don't need
+    /// its merge semantics. Use [`get_typed_value`](Self::get_typed_value)
+    /// to tell the two apart.
+    pub fn get_int(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some((Value::Scalar(s), _)) =
+            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
+        {
+            match s.as_ref() {
+                ScalarValue::Int(i) => return Ok(Some(*i)),
+                ScalarValue::Counter(c) => return Ok(Some(i64::from(c))),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Insert an integer value and return the raw change bytes.
+    pub fn put_int_with_change(
+        &mut self,
+        path: &str,
+        value: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let mut tx = self.doc.transaction();
+
+        if segments.is_empty() {
+            return Err(AutomergeError::Fail);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+
+        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Insert a double value using a path (e.g., "metrics.temperature", "temps[0]", or "$.metrics.temperature").
+    /// Creates intermediate maps as needed. Array indices must already exist.
+    pub fn put_double(&mut self, path: &str, value: f64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.put_double_with_change(path, value).map(|_| ())
+    }
+
+    /// Insert a double value and return the raw change bytes.
+    pub fn put_double_with_change(
+        &mut self,
+        path: &str,
+        value: f64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let mut tx = self.doc.transaction();
+
+        if segments.is_empty() {
+            return Err(AutomergeError::Fail);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+
+        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Retrieve a double value using a path (e.g., "metrics.temperature", "temps[0]", or "$.metrics.temperature").
+    pub fn get_double(&self, path: &str) -> Result<Option<f64>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some((Value::Scalar(s), _)) =
+            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
+        {
+            if let ScalarValue::F64(f) = s.as_ref() {
+                return Ok(Some(*f));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Insert a boolean value using a path (e.g., "flags.active", "flags\[0\]", or "$.flags.active").
+    /// Creates intermediate maps as needed. Array indices must already exist.
+    pub fn put_bool(&mut self, path: &str, value: bool) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.put_bool_with_change(path, value).map(|_| ())
+    }
+
+    /// Retrieve a boolean value using a path (e.g., "flags.active", "flags\[0\]", or "$.flags.active").
+    pub fn get_bool(&self, path: &str) -> Result<Option<bool>, AutomergeError> {
+        self.get_bool_with(path, false)
+    }
+
+    /// Retrieve a boolean, also accepting loosely typed stand-ins.
+    ///
+    /// Besides real booleans, the integers `0` and `1` and the strings
+    /// `"true"`, `"false"`, `"1"` and `"0"` (case-insensitive) are read as
+    /// booleans. Any other value still returns `None`, as with [`get_bool`].
+    ///
+    /// [`get_bool`]: Self::get_bool
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_int("flags.active", 1).unwrap();
+    /// client.put_text("flags.admin", "false").unwrap();
+    ///
+    /// assert_eq!(client.get_bool("flags.active").unwrap(), None);
+    /// assert_eq!(client.get_bool_coerced("flags.active").unwrap(), Some(true));
+    /// assert_eq!(client.get_bool_coerced("flags.admin").unwrap(), Some(false));
+    /// ```
+    pub fn get_bool_coerced(&self, path: &str) -> Result<Option<bool>, AutomergeError> {
+        self.get_bool_with(path, true)
+    }
+
+    fn get_bool_with(&self, path: &str, coerce: bool) -> Result<Option<bool>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some((Value::Scalar(s), _)) =
+            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
+        {
+            return Ok(match s.as_ref() {
+                ScalarValue::Boolean(b) => Some(*b),
+                _ if !coerce => None,
+                ScalarValue::Int(i @ (0 | 1)) => Some(*i == 1),
+                ScalarValue::Uint(u @ (0 | 1)) => Some(*u == 1),
+                ScalarValue::Str(t) => match t.to_lowercase().as_str() {
+                    "true" | "1" => Some(true),
+                    "false" | "0" => Some(false),
+                    _ => None,
+                },
+                _ => None,
+            });
+        }
+        Ok(None)
+    }
+
+    /// Insert a boolean value and return the raw change bytes.
+    pub fn put_bool_with_change(
+        &mut self,
+        path: &str,
+        value: bool,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let mut tx = self.doc.transaction();
+
+        if segments.is_empty() {
+            return Err(AutomergeError::Fail);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+
+        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Insert a counter value using a path (e.g., "stats.views", "counters[0]", or "$.stats.views").
+    /// Creates intermediate maps as needed. Array indices must already exist.
+    ///
+    /// Counters are CRDT values that support increment operations with proper
+    /// conflict resolution across distributed systems.
+    pub fn put_counter(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.put_counter_with_change(path, value).map(|_| ())
+    }
+
+    /// Insert a counter value and return the raw change bytes.
+    pub fn put_counter_with_change(
+        &mut self,
+        path: &str,
+        value: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let mut tx = self.doc.transaction();
+
+        if segments.is_empty() {
+            return Err(AutomergeError::Fail);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+
+        // Put counter value
+        match &field_name[0] {
+            PathSegment::Key(key) => {
+                tx.put(&parent_obj, key.as_str(), ScalarValue::Counter(value.into()))?;
+            }
+            PathSegment::Index(idx) => {
+                tx.put(&parent_obj, *idx, ScalarValue::Counter(value.into()))?;
+            }
+        }
+
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Retrieve a counter value using a path (e.g., "stats.views", "counters[0]", or "$.stats.views").
+    /// Returns the current counter value as an i64.
+    pub fn get_counter(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some((Value::Scalar(s), _)) =
+            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
+        {
+            if let ScalarValue::Counter(c) = s.as_ref() {
+                return Ok(Some(i64::from(c)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get a value with type information from the specified path.
+    ///
+    /// This method extracts values preserving their Automerge types, which is
+    /// useful for building JSON index documents with proper type representation.
+    /// A map leaves out expiry deadlines and fields whose deadline has passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(TypedValue)` if the path exists, `None` otherwise.
+    pub fn get_typed_value(&self, path: &str) -> Result<Option<TypedValue>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        match get_value_from_parent(&self.doc, &parent_obj, &field_name[0])? {
+            Some((Value::Scalar(s), _)) => Ok(Some(TypedValue::from(s.as_ref()))),
+            Some((Value::Object(obj_type), obj_id)) => {
+                // Handle Text objects
+                if obj_type == automerge::ObjType::Text {
+                    let text = self.doc.text(&obj_id)?;
+                    return Ok(Some(TypedValue::Text(text)));
+                }
+
+                // Handle List objects - return as Array
+                if obj_type == automerge::ObjType::List {
+                    let mut arr = Vec::new();
+                    let len = self.doc.length(&obj_id);
+                    for i in 0..len {
+                        if let Some((value, value_obj_id)) = self.doc.get(&obj_id, i)? {
+                            if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
+                                arr.push(typed_val);
+                            }
+                        }
+                    }
+                    return Ok(Some(TypedValue::Array(arr)));
+                }
+
+                // Handle Map objects
+                if obj_type == automerge::ObjType::Map {
+                    let now_ms = Utc::now().timestamp_millis();
+                    let mut map = std::collections::HashMap::new();
+                    for key in self.doc.keys(&obj_id) {
+                        if let Some((value, value_obj_id)) = self.doc.get(&obj_id, &key)? {
+                            if is_hidden_field(&self.doc, &obj_id, &key, &value, now_ms)? {
+                                continue;
+                            }
+                            if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
+                                map.insert(key, typed_val);
+                            }
+                        }
+                    }
+                    return Ok(Some(TypedValue::Object(map)));
+                }
+
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieve every concurrent value at `path`, each with the hex id of
+    /// the actor that wrote it.
+    ///
+    /// After concurrent writes to the same key or list index are merged,
+    /// Automerge keeps all of them and picks a winner for plain reads. This
+    /// returns them all in Automerge's order, which ends with the winner
+    /// (the value [`get_typed_value`](Self::get_typed_value) returns). A
+    /// value with no conflict comes back as a single entry, and a missing
+    /// path as an empty list.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, TypedValue};
+    ///
+    /// let mut client = RedisAutomergeClient::with_actor(&[0xaa]);
+    /// client.put_text("title", "Draft").unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.get_all_typed("title").unwrap(),
+    ///     vec![("aa".to_string(), TypedValue::Text("Draft".to_string()))]
+    /// );
+    /// ```
+    pub fn get_all_typed(&self, path: &str) -> Result<Vec<(String, TypedValue)>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        if segments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(Vec::new()),
+            }
+        };
+
+        let values = match &field_name[0] {
+            PathSegment::Key(key) => self.doc.get_all(&parent_obj, key.as_str())?,
+            PathSegment::Index(idx) => {
+                if *idx >= self.doc.length(&parent_obj) {
+                    return Ok(Vec::new());
+                }
+                self.doc.get_all(&parent_obj, *idx)?
+            }
+        };
+
+        let mut result = Vec::with_capacity(values.len());
+        for (value, id) in values {
+            // The id of a value is the id of the op that wrote it, so its
+            // actor is the value's author.
+            let actor = match &id {
+                ObjId::Id(_, actor, _) => actor.to_hex_string(),
+                ObjId::Root => continue,
+            };
+            if let Some(typed) = self.value_to_typed(&value, &id)? {
+                result.push((actor, typed));
+            }
+        }
+        Ok(result)
+    }
+
+    /// The change that wrote the value currently visible at `path`.
+    ///
+    /// This follows the op behind the value [`get`](Self::get_typed_value)
+    /// returns, which after a conflict is the winning one. For a map, list
+    /// or text object that is the op that created the object, so edits
+    /// inside it are not counted; likewise a counter reports the change
+    /// that created it, not its latest increment. Returns `None` for a
+    /// missing path or the root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::with_actor(&[0xaa]);
+    /// client.put_int("config.port", 8080).unwrap();
+    ///
+    /// let writer = client.last_writer("config.port").unwrap().unwrap();
+    /// assert_eq!(writer.actor, "aa");
+    /// assert_eq!(vec![writer.hash], client.get_heads());
+    /// ```
+    pub fn last_writer(&self, path: &str) -> Result<Option<LastWriter>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let (parent_path, field_name) = match segments.split_last() {
+            Some((last, parent)) => (parent, last),
+            None => return Ok(None),
+        };
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+        // The id of a value is the id of the op that wrote it: a counter
+        // within one change by its actor.
+        let (counter, actor) = match get_value_from_parent(&self.doc, &parent_obj, field_name)? {
+            Some((_, ObjId::Id(counter, actor, _))) => (counter, actor),
+            _ => return Ok(None),
+        };
+        Ok(self
+            .doc
+            .get_changes_meta(&[])
+            .into_iter()
+            .find(|c| *c.actor == actor && c.start_op <= counter && counter <= c.max_op)
+            .map(|c| LastWriter {
+                actor: actor.to_hex_string(),
+                hash: c.hash,
+                timestamp: c.timestamp,
+            }))
+    }
+
+    /// Every operation in the history that touched `path`, oldest first.
+    ///
+    /// For a map field that is every put, delete and increment of that
+    /// key, including ops from before it was last deleted and the losing
+    /// side of conflicts. For a list index it is the insert that created
+    /// the element currently there and every later op on that element.
+    /// If the path holds a map, list or text object, the ops made directly
+    /// inside it are listed too (but not ops in objects nested further
+    /// down). Nothing in the document is modified.
+    ///
+    /// This is a diagnostic for understanding merge outcomes. It decodes
+    /// the whole change history, so it costs time proportional to the
+    /// number of ops ever made in the document.
+    ///
+    /// Returns `None` if the parent of `path` does not exist, the index is
+    /// out of range, or `path` is the root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::with_actor(&[0xaa]);
+    /// client.put_int("config.port", 80).unwrap();
+    /// client.put_int("config.port", 8080).unwrap();
+    ///
+    /// let ops = client.path_ops("config.port").unwrap().unwrap();
+    /// assert_eq!(ops.len(), 2);
+    /// assert_eq!(ops[1].action, "put");
+    /// assert_eq!(ops[1].value.as_deref(), Some("8080"));
+    /// ```
+    pub fn path_ops(&self, path: &str) -> Result<Option<Vec<PathOp>>, AutomergeError> {
+        use automerge::legacy::{ElementId, Key, ObjectId, OpId, OpType};
+
+        let segments = parse_path(path)?;
+        let (field_name, parent_path) = match segments.split_last() {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+        let parent_obj = if parent_path.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+        let legacy_id = |id: &ObjId| match id {
+            ObjId::Id(counter, actor, _) => Some(OpId(*counter, actor.clone())),
+            ObjId::Root => None,
+        };
+        let legacy_obj = |id: &ObjId| match legacy_id(id) {
+            Some(op_id) => ObjectId::Id(op_id),
+            None => ObjectId::Root,
+        };
+        let parent = legacy_obj(&parent_obj);
+        let current = get_value_from_parent(&self.doc, &parent_obj, field_name)?;
+        let inner = match &current {
+            Some((Value::Object(_), id)) => Some(legacy_obj(id)),
+            _ => None,
+        };
+
+        // An op's id is its change's start op plus its position in it.
+        let changes: Vec<_> = self
+            .doc
+            .get_changes(&[])
+            .iter()
+            .map(Change::decode)
+            .collect();
+        let ops = || {
+            changes.iter().flat_map(|change| {
+                change.operations.iter().enumerate().map(move |(i, op)| {
+                    let id = OpId(change.start_op.get() + i as u64, change.actor_id.clone());
+                    (change, id, op)
+                })
+            })
+        };
+
+        // A list element is named by the id of the insert that created it,
+        // which the op behind the current value either is or points at.
+        let element = match field_name {
+            PathSegment::Key(_) => None,
+            PathSegment::Index(_) => {
+                let value_id = match current.as_ref().and_then(|(_, id)| legacy_id(id)) {
+                    Some(id) => id,
+                    None => return Ok(None),
+                };
+                match ops().find(|(_, id, _)| *id == value_id) {
+                    Some((_, id, op)) if op.insert => Some(id),
+                    Some((_, _, op)) => match &op.key {
+                        Key::Seq(ElementId::Id(elem)) => Some(elem.clone()),
+                        _ => return Ok(None),
+                    },
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let touches = |id: &OpId, op: &automerge::legacy::Op| {
+            if inner.as_ref() == Some(&op.obj) {
+                return true;
+            }
+            if op.obj != parent {
+                return false;
+            }
+            match (field_name, &element, &op.key) {
+                (PathSegment::Key(name), _, Key::Map(key)) => key.as_str() == name,
+                // An insert's key is the element it follows, not its own.
+                (PathSegment::Index(_), Some(elem), _) if op.insert => id == elem,
+                (PathSegment::Index(_), Some(elem), Key::Seq(ElementId::Id(target))) => {
+                    target == elem
+                }
+                _ => false,
+            }
+        };
+
+        let mut result = Vec::new();
+        for (change, id, op) in ops() {
+            if !touches(&id, op) {
+                continue;
+            }
+            let (action, value) = match &op.action {
+                OpType::Put(value) => ("put", Some(scalar_text(value))),
+                OpType::Make(obj_type) => ("make", Some(obj_type.to_string())),
+                OpType::Delete => ("delete", None),
+                OpType::Increment(by) => ("increment", Some(by.to_string())),
+                OpType::MarkBegin(mark) => ("mark", Some(mark.name.to_string())),
+                OpType::MarkEnd(_) => ("mark", None),
+            };
+            result.push(PathOp {
+                action: if op.insert { "insert" } else { action },
+                actor: change.actor_id.to_hex_string(),
+                seq: change.seq,
+                value,
+            });
+        }
+        Ok(Some(result))
+    }
+
+    /// Helper method to convert Automerge Value to TypedValue
+    fn value_to_typed(
+        &self,
+        value: &Value,
+        obj_id: &ObjId,
+    ) -> Result<Option<TypedValue>, AutomergeError> {
+        match value {
+            Value::Scalar(s) => Ok(Some(TypedValue::from(s.as_ref()))),
+            Value::Object(obj_type) => {
+                // Handle Text objects
+                if *obj_type == automerge::ObjType::Text {
+                    let text = self.doc.text(obj_id)?;
+                    return Ok(Some(TypedValue::Text(text)));
+                }
+
+                // Handle List objects
+                if *obj_type == automerge::ObjType::List {
+                    let mut arr = Vec::new();
+                    let len = self.doc.length(obj_id);
+                    for i in 0..len {
+                        if let Some((val, val_obj_id)) = self.doc.get(obj_id, i)? {
+                            if let Some(typed_val) = self.value_to_typed(&val, &val_obj_id)? {
+                                arr.push(typed_val);
+                            }
+                        }
+                    }
+                    return Ok(Some(TypedValue::Array(arr)));
+                }
+
+                // Handle Map objects
+                if *obj_type == automerge::ObjType::Map {
+                    let now_ms = Utc::now().timestamp_millis();
+                    let mut map = std::collections::HashMap::new();
+                    for key in self.doc.keys(obj_id) {
+                        if let Some((val, val_obj_id)) = self.doc.get(obj_id, &key)? {
+                            if is_hidden_field(&self.doc, obj_id, &key, &val, now_ms)? {
+                                continue;
+                            }
+                            if let Some(typed_val) = self.value_to_typed(&val, &val_obj_id)? {
+                                map.insert(key, typed_val);
+                            }
+                        }
+                    }
+                    return Ok(Some(TypedValue::Object(map)));
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Get a single list element with its type, by index.
+    ///
+    /// Unlike `get_text("list[5]")` and friends, this distinguishes a missing
+    /// element from one of a different type: any element is returned as a
+    /// [`TypedValue`], and `None` means only that `index` is out of range.
+    /// Negative indices count from the end (`-1` is the last element).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    /// * `index` - Element position; negative values count from the end
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, TypedValue};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("items").unwrap();
+    /// client.append_text("items", "a").unwrap();
+    /// client.append_int("items", 2).unwrap();
+    ///
+    /// assert_eq!(client.list_get("items", -1).unwrap(), Some(TypedValue::Int(2)));
+    /// assert_eq!(client.list_get("items", 5).unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_get(&self, path: &str, index: i64) -> Result<Option<TypedValue>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        let len = self.doc.length(&list_obj);
+        let position = if index < 0 {
+            match usize::try_from(index.unsigned_abs()) {
+                Ok(back) if back <= len => len - back,
+                _ => return Ok(None),
+            }
+        } else {
+            match usize::try_from(index) {
+                Ok(i) if i < len => i,
+                _ => return Ok(None),
+            }
+        };
+
+        match self.doc.get(&list_obj, position)? {
+            Some((value, value_obj_id)) => Ok(Some(
+                self.value_to_typed(&value, &value_obj_id)?
+                    .unwrap_or(TypedValue::Null),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Find the first map in a list whose `field` equals `value`.
+    ///
+    /// Elements that are not maps, or lack `field`, are skipped. Text
+    /// values, both scalar strings and text objects, must equal `value`
+    /// exactly; numbers and booleans match their usual spelling, so `"42"`
+    /// finds an integer 42 and `"true"` a boolean true.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    /// * `field` - Key to compare in each map element
+    /// * `value` - Value to look for
+    ///
+    /// # Returns
+    ///
+    /// The index of the first matching element, or `None` if nothing
+    /// matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client = RedisAutomergeClient::from_json(
+    ///     r#"{"users":[{"id":"u1","name":"Alice"},{"id":"u2","name":"Bob"}]}"#,
+    /// ).unwrap();
+    /// assert_eq!(client.list_find("users", "id", "u2").unwrap(), Some(1));
+    /// assert_eq!(client.list_find("users", "id", "u3").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_find(
+        &self,
+        path: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<Option<usize>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        for i in 0..self.doc.length(&list_obj) {
+            let element = match self.doc.get(&list_obj, i)? {
+                Some((Value::Object(automerge::ObjType::Map), element)) => element,
+                _ => continue,
+            };
+            let matches = match self.doc.get(&element, field)? {
+                Some((Value::Scalar(s), _)) => scalar_matches_str(&s, value),
+                Some((Value::Object(automerge::ObjType::Text), text)) => {
+                    self.doc.text(&text)? == value
+                }
+                _ => false,
+            };
+            if matches {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The maps in a list whose `field` compares to `value` as `op` asks,
+    /// as a JSON array in list order.
+    ///
+    /// `Eq` matches as [`list_find`](Self::list_find) does and `Ne` is its
+    /// negation. `Lt` and `Gt` compare numbers (integers, doubles, counters
+    /// and timestamps) numerically when `value` parses as a number, and
+    /// text lexicographically by bytes; other values never match them.
+    /// Elements that are not maps, or lack `field`, are skipped for every
+    /// op. This is a linear scan of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{QueryOp, RedisAutomergeClient};
+    ///
+    /// let client = RedisAutomergeClient::from_json(
+    ///     r#"{"users":[{"name":"Alice","age":30},{"name":"Bob","age":25}]}"#,
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     client.list_query("users", "age", QueryOp::Gt, "26").unwrap(),
+    ///     r#"[{"age":30,"name":"Alice"}]"#
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_query(
+        &self,
+        path: &str,
+        field: &str,
+        op: QueryOp,
+        value: &str,
+    ) -> Result<String, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        let mut matches = Vec::new();
+        for i in 0..self.doc.length(&list_obj) {
+            let (element, element_id) = match self.doc.get(&list_obj, i)? {
+                Some((element @ Value::Object(automerge::ObjType::Map), id)) => (element, id),
+                _ => continue,
+            };
+            let field_value = match self.doc.get(&element_id, field)? {
+                Some((Value::Scalar(s), _)) => s.into_owned(),
+                Some((Value::Object(automerge::ObjType::Text), text)) => {
+                    ScalarValue::Str(self.doc.text(&text)?.into())
+                }
+                _ => continue,
+            };
+            let keep = match op {
+                QueryOp::Eq => scalar_matches_str(&field_value, value),
+                QueryOp::Ne => !scalar_matches_str(&field_value, value),
+                QueryOp::Lt => {
+                    scalar_cmp_str(&field_value, value) == Some(std::cmp::Ordering::Less)
+                }
+                QueryOp::Gt => {
+                    scalar_cmp_str(&field_value, value) == Some(std::cmp::Ordering::Greater)
+                }
+            };
+            if keep {
+                if let Some(typed) = self.value_to_typed(&element, &element_id)? {
+                    matches.push(typed.to_json());
+                }
+            }
+        }
+        serde_json::to_string(&matches).map_err(|_| AutomergeError::Fail)
+    }
+
+    /// Whether the last element of the list at `path` equals `value`,
+    /// compared as [`ListMatch::Exact`] compares.
+    ///
+    /// Returns `false` for an empty list, or if the path doesn't exist or
+    /// isn't a list.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use automerge::ScalarValue;
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("status").unwrap();
+    /// client.append_text("status", "up").unwrap();
+    /// client.append_text("status", "down").unwrap();
+    ///
+    /// assert!(client.list_ends_with("status", &ScalarValue::from("down")).unwrap());
+    /// assert!(!client.list_ends_with("status", &ScalarValue::from("up")).unwrap());
+    /// ```
+    pub fn list_ends_with(&self, path: &str, value: &ScalarValue) -> Result<bool, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = match navigate_path_read(&self.doc, &segments)? {
+            Some(obj) if self.doc.object_type(&obj)? == automerge::ObjType::List => obj,
+            _ => return Ok(false),
+        };
+        match self.doc.length(&list_obj) {
+            0 => Ok(false),
+            len => self.element_equals(&list_obj, len - 1, value, ListMatch::Exact),
+        }
+    }
+
+    /// Whether element `index` of `list_obj` matches `value` under
+    /// `matching`, with text objects matching string values.
+    fn element_equals(
+        &self,
+        list_obj: &ObjId,
+        index: usize,
+        value: &ScalarValue,
+        matching: ListMatch,
+    ) -> Result<bool, AutomergeError> {
+        Ok(match (self.doc.get(list_obj, index)?, value, matching) {
+            (Some((Value::Scalar(s), _)), ScalarValue::Str(v), ListMatch::Loose) => {
+                scalar_matches_str(&s, v)
+            }
+            (Some((Value::Scalar(s), _)), _, _) => s.as_ref() == value,
+            (Some((Value::Object(automerge::ObjType::Text), text)), ScalarValue::Str(v), _) => {
+                self.doc.text(&text)? == v.as_str()
+            }
+            _ => false,
+        })
+    }
+
+    /// Whether any element of the list at `path` matches `value`, compared
+    /// as `matching` says. Text objects match string values by their
+    /// content; nested maps and lists never match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use automerge::ScalarValue;
+    /// use redis_automerge::ext::{ListMatch, RedisAutomergeClient};
+    ///
+    /// let client = RedisAutomergeClient::from_json(r#"{"tags":["red",3,true]}"#).unwrap();
+    /// let three = ScalarValue::from("3");
+    /// assert!(client.list_contains("tags", &three, ListMatch::Loose).unwrap());
+    /// assert!(!client.list_contains("tags", &three, ListMatch::Exact).unwrap());
+    /// assert!(client.list_contains("tags", &ScalarValue::Int(3), ListMatch::Exact).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_contains(
+        &self,
+        path: &str,
+        value: &ScalarValue,
+        matching: ListMatch,
+    ) -> Result<bool, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        for i in 0..self.doc.length(&list_obj) {
+            if self.element_equals(&list_obj, i, value, matching)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Copy a range of a list into a new, independent document.
+    ///
+    /// `start` and `stop` follow Redis `LRANGE`: both are inclusive,
+    /// negative values count from the end, and out-of-range values are
+    /// clamped. The elements are stored as a list under
+    /// [`LIST_SLICE_KEY`] in the new document, which shares no history with
+    /// this one. Nested objects are deep-copied and counters keep their
+    /// current value.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    /// * `start` - First position to copy
+    /// * `stop` - Last position to copy
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("events").unwrap();
+    /// for event in ["a", "b", "c", "d"] {
+    ///     client.append_text("events", event).unwrap();
+    /// }
+    ///
+    /// let window = client.list_slice("events", 1, -2).unwrap();
+    /// assert_eq!(window.list_len("items").unwrap(), Some(2));
+    /// assert_eq!(window.get_text("items[0]").unwrap(), Some("b".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_slice(
+        &self,
+        path: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<RedisAutomergeClient, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        let range = list_range(self.doc.length(&list_obj), start, stop);
+
+        let mut slice = RedisAutomergeClient::new();
+        let mut tx = slice.doc.transaction();
+        let items = tx.put_object(ROOT, LIST_SLICE_KEY, automerge::ObjType::List)?;
+        copy_list_range(&self.doc, &list_obj, range, &mut tx, &items)?;
+        let (_hash, _patch) = tx.commit();
+        Ok(slice)
+    }
+
+    /// Keep only the elements of the list at `path` between `start` and
+    /// `stop`, deleting the rest in one transaction.
+    ///
+    /// `start` and `stop` follow Redis `LTRIM`: both are inclusive,
+    /// negative values count from the end, and out-of-range values are
+    /// clamped; an empty range empties the list. The kept elements are not
+    /// rewritten, but trimming still doesn't merge like a cap would:
+    /// elements other replicas insert concurrently survive the trim, so the
+    /// merged list can be longer than the window, and concurrent edits to
+    /// trimmed elements are lost.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("log").unwrap();
+    /// for event in ["a", "b", "c", "d"] {
+    ///     client.append_text("log", event).unwrap();
+    /// }
+    ///
+    /// // Keep the last two entries.
+    /// client.list_trim("log", -2, -1).unwrap();
+    /// assert_eq!(client.list_len("log").unwrap(), Some(2));
+    /// assert_eq!(client.get_text("log[0]").unwrap(), Some("c".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_trim(&mut self, path: &str, start: i64, stop: i64) -> Result<(), AutomergeError> {
+        self.list_trim_with_change(path, start, stop).map(|_| ())
+    }
+
+    /// Trim a list to a range and return the raw change bytes, or `None`
+    /// if every element is already inside the range.
+    pub fn list_trim_with_change(
+        &mut self,
+        path: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
+
+        let len = self.doc.length(&list_obj);
+        let keep = list_range(len, start, stop);
+        if keep.len() == len {
+            return Ok(None);
+        }
+
+        let mut tx = self.doc.transaction();
+        // Delete the tail first so the head's indices stay valid.
+        for i in (keep.end..len).rev() {
+            tx.delete(&list_obj, i)?;
+        }
+        for i in (0..keep.start).rev() {
+            tx.delete(&list_obj, i)?;
+        }
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get all values from a list at the specified path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<TypedValue>)` if the path points to a list, `None` otherwise.
+    pub fn get_list_values(&self, path: &str) -> Result<Option<Vec<TypedValue>>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        // Check if it's a list
+        let obj_type = self.doc.object_type(&list_obj)?;
+        if obj_type == automerge::ObjType::List {
+            let mut values = Vec::new();
+            let len = self.doc.length(&list_obj);
+
+            for i in 0..len {
+                if let Some((value, value_obj_id)) = self.doc.get(&list_obj, i)? {
+                    if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
+                        values.push(typed_val);
+                    }
+                }
+            }
+
+            return Ok(Some(values));
+        }
+
+        Ok(None)
+    }
+
+    /// Get the integer elements of a list at the specified path, in order.
+    ///
+    /// Elements of any other type (text, doubles, counters, nested objects)
+    /// are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<i64>)` if the path points to a list, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client = RedisAutomergeClient::from_json(r#"{"vals":[1,"a",2.5,3]}"#).unwrap();
+    /// assert_eq!(client.list_ints("vals").unwrap(), Some(vec![1, 3]));
+    /// assert_eq!(client.list_ints("missing").unwrap(), None);
+    /// ```
+    pub fn list_ints(&self, path: &str) -> Result<Option<Vec<i64>>, AutomergeError> {
+        Ok(self.get_list_values(path)?.map(|values| {
+            values
+                .into_iter()
+                .filter_map(|value| match value {
+                    TypedValue::Int(n) => Some(n),
+                    _ => None,
+                })
+                .collect()
+        }))
+    }
+
+    /// Count the distinct scalar elements of a list at the specified path.
+    ///
+    /// Elements are compared on type and value, so `1`, `1.0` and `"1"`
+    /// are three distinct values, while `0.0` and `-0.0` are one. Counters
+    /// compare by their current value. Nested objects are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the list
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(count)` if the path points to a list, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client = RedisAutomergeClient::from_json(r#"{"vals":[1,"1",1,"a","1"]}"#).unwrap();
+    /// assert_eq!(client.list_distinct_count("vals").unwrap(), Some(3));
+    /// ```
+    pub fn list_distinct_count(&self, path: &str) -> Result<Option<usize>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Ok(None);
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..self.doc.length(&list_obj) {
+            if let Some((Value::Scalar(s), _)) = self.doc.get(&list_obj, i)? {
+                seen.insert(scalar_identity(&s));
+            }
+        }
+        Ok(Some(seen.len()))
+    }
+
+    /// Get all keys from a map at the specified path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the map
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<String>)` if the path points to a map, `None` otherwise.
+    pub fn get_map_keys(&self, path: &str) -> Result<Option<Vec<String>>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        let map_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        // Check if it's a map
+        let obj_type = self.doc.object_type(&map_obj)?;
+        if obj_type == automerge::ObjType::Map {
+            let keys: Vec<String> = self.doc.keys(&map_obj).collect();
+            return Ok(Some(keys));
+        }
+
+        Ok(None)
+    }
+
+    /// Increment a counter at the specified path by the given delta.
     ///
-    /// Like `put_text()` but returns Automerge change bytes that can
-    /// be published to other clients for real-time synchronization.
+    /// This uses Automerge's CRDT counter increment operation, which properly
+    /// merges concurrent increments from different replicas.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path to the field
-    /// * `value` - Text value to insert
-    ///
-    /// # Returns
-    ///
-    /// - `Some(Vec<u8>)` - Raw change bytes if a change was generated
-    /// - `None` - If no change was needed
+    /// * `path` - Path to the counter field
+    /// * `delta` - Amount to increment (can be negative to decrement)
     ///
     /// # Examples
     ///
@@ -637,91 +4341,113 @@ impl RedisAutomergeClient {
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
     /// let mut client = RedisAutomergeClient::new();
-    /// let change = client.put_text_with_change("field", "hello").unwrap();
+    /// client.put_counter("views", 0).unwrap();
+    /// client.inc_counter("views", 1).unwrap();
+    /// client.inc_counter("views", 5).unwrap();
     ///
-    /// if let Some(change_bytes) = change {
-    ///     // Publish to other clients
-    /// }
+    /// assert_eq!(client.get_counter("views").unwrap(), Some(6));
     /// ```
-    pub fn put_text_with_change(
+    pub fn inc_counter(&mut self, path: &str, delta: i64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.inc_counter_with_change(path, delta).map(|_| ())
+    }
+
+    /// Increment a counter and return the raw change bytes.
+    pub fn inc_counter_with_change(
         &mut self,
         path: &str,
-        value: &str,
+        delta: i64,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
-        let mut tx = self.doc.transaction();
 
         if segments.is_empty() {
             return Err(AutomergeError::Fail);
         }
 
         let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
-
-        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
-        let (hash, _patch) = tx.commit();
-
-        if let Some(h) = hash {
-            if let Some(change) = self.doc.get_change_by_hash(&h) {
-                return Ok(Some(change.raw_bytes().to_vec()));
-            }
-        }
-
-        Ok(None)
-    }
-
-    /// Insert an integer value using a path (e.g., "user.age", "users[0].age", or "$.user.age").
-    /// Creates intermediate maps as needed. Array indices must already exist.
-    pub fn put_int(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.put_int_with_change(path, value).map(|_| ())
-    }
-
-    /// Retrieve an integer value using a path (e.g., "user.age", "users[0].age", or "$.user.age").
-    pub fn get_int(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
-        let segments = parse_path(path)?;
 
-        if segments.is_empty() {
-            return Ok(None);
-        }
-
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        // Get parent object (don't create it if it doesn't exist for increment)
         let parent_obj = if parent_path.is_empty() {
             ROOT
         } else {
             match navigate_path_read(&self.doc, parent_path)? {
                 Some(obj) => obj,
-                None => return Ok(None),
+                None => return Err(AutomergeError::Fail),
             }
         };
 
-        if let Some((Value::Scalar(s), _)) =
-            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
-        {
-            if let ScalarValue::Int(i) = s.as_ref() {
-                return Ok(Some(*i));
+        let mut tx = self.doc.transaction();
+
+        // Increment the counter
+        match &field_name[0] {
+            PathSegment::Key(key) => {
+                tx.increment(&parent_obj, key.as_str(), delta)?;
+            }
+            PathSegment::Index(idx) => {
+                tx.increment(&parent_obj, *idx, delta)?;
+            }
+        }
+
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
             }
         }
+
         Ok(None)
     }
 
-    /// Insert an integer value and return the raw change bytes.
-    pub fn put_int_with_change(
+    /// Increment several counters in a single change.
+    ///
+    /// Each `(path, delta)` pair is applied in order, so a path given twice
+    /// is incremented twice. A counter missing from a map is created with
+    /// `delta` as its value, along with any missing parent maps; list
+    /// elements must already exist. Either every increment is applied or,
+    /// if any fails (for example because a path holds something other than
+    /// a counter), none is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_counter("score.home", 1).unwrap();
+    /// client.inc_counters(&[("score.home", 2), ("score.away", 1)]).unwrap();
+    ///
+    /// assert_eq!(client.get_counter("score.home").unwrap(), Some(3));
+    /// assert_eq!(client.get_counter("score.away").unwrap(), Some(1));
+    /// ```
+    pub fn inc_counters(&mut self, increments: &[(&str, i64)]) -> Result<(), AutomergeError> {
+        self.inc_counters_with_change(increments).map(|_| ())
+    }
+
+    /// Increment several counters in a single change and return the raw
+    /// change bytes.
+    pub fn inc_counters_with_change(
         &mut self,
-        path: &str,
-        value: i64,
+        increments: &[(&str, i64)],
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
-        let segments = parse_path(path)?;
-        let mut tx = self.doc.transaction();
-
-        if segments.is_empty() {
-            return Err(AutomergeError::Fail);
+        let mut parsed = Vec::with_capacity(increments.len());
+        for (path, delta) in increments {
+            let mut segments = parse_path(path)?;
+            let field = segments.pop().ok_or(AutomergeError::Fail)?;
+            parsed.push((segments, field, *delta));
         }
 
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
-
-        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let mut tx = self.doc.transaction();
+        for (parent_path, field, delta) in parsed {
+            let parent_obj = navigate_or_create_path(&mut tx, &parent_path)?;
+            match &field {
+                PathSegment::Key(key) if tx.get(&parent_obj, key.as_str())?.is_none() => {
+                    tx.put(&parent_obj, key.as_str(), ScalarValue::counter(delta))?;
+                }
+                PathSegment::Key(key) => tx.increment(&parent_obj, key.as_str(), delta)?,
+                PathSegment::Index(idx) => tx.increment(&parent_obj, *idx, delta)?,
+            }
+        }
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -733,18 +4459,36 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Insert a double value using a path (e.g., "metrics.temperature", "temps[0]", or "$.metrics.temperature").
+    /// Insert a timestamp value using a path (e.g., "event.created_at", "timestamps[0]", or "$.event.timestamp").
     /// Creates intermediate maps as needed. Array indices must already exist.
-    pub fn put_double(&mut self, path: &str, value: f64) -> Result<(), AutomergeError> {
+    ///
+    /// Timestamps are stored as i64 values representing milliseconds since Unix epoch (UTC).
+    /// They will be rendered as ISO 8601 UTC datetime strings when exported to JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the field
+    /// * `value` - Unix timestamp in milliseconds
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// // Set timestamp to 2024-01-01 00:00:00 UTC (1704067200000 milliseconds)
+    /// client.put_timestamp("created_at", 1704067200000).unwrap();
+    /// ```
+    pub fn put_timestamp(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
         // Audit #32: delegate to the `_with_change` variant.
-        self.put_double_with_change(path, value).map(|_| ())
+        self.put_timestamp_with_change(path, value).map(|_| ())
     }
 
-    /// Insert a double value and return the raw change bytes.
-    pub fn put_double_with_change(
+    /// Insert a timestamp value and return the raw change bytes.
+    pub fn put_timestamp_with_change(
         &mut self,
         path: &str,
-        value: f64,
+        value: i64,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
         let mut tx = self.doc.transaction();
@@ -756,7 +4500,16 @@ impl RedisAutomergeClient {
         let (parent_path, field_name) = segments.split_at(segments.len() - 1);
         let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
 
-        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        // Put timestamp value
+        match &field_name[0] {
+            PathSegment::Key(key) => {
+                tx.put(&parent_obj, key.as_str(), ScalarValue::Timestamp(value))?;
+            }
+            PathSegment::Index(idx) => {
+                tx.put(&parent_obj, *idx, ScalarValue::Timestamp(value))?;
+            }
+        }
+
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -768,8 +4521,9 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Retrieve a double value using a path (e.g., "metrics.temperature", "temps[0]", or "$.metrics.temperature").
-    pub fn get_double(&self, path: &str) -> Result<Option<f64>, AutomergeError> {
+    /// Retrieve a timestamp value using a path (e.g., "event.created_at", "timestamps[0]", or "$.event.timestamp").
+    /// Returns the timestamp as an i64 (milliseconds since Unix epoch).
+    pub fn get_timestamp(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
         let segments = parse_path(path)?;
 
         if segments.is_empty() {
@@ -789,65 +4543,239 @@ impl RedisAutomergeClient {
         if let Some((Value::Scalar(s), _)) =
             get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
         {
-            if let ScalarValue::F64(f) = s.as_ref() {
-                return Ok(Some(*f));
+            if let ScalarValue::Timestamp(ts) = s.as_ref() {
+                return Ok(Some(*ts));
             }
         }
         Ok(None)
     }
 
-    /// Insert a boolean value using a path (e.g., "flags.active", "flags\[0\]", or "$.flags.active").
-    /// Creates intermediate maps as needed. Array indices must already exist.
-    pub fn put_bool(&mut self, path: &str, value: bool) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.put_bool_with_change(path, value).map(|_| ())
+    /// Apply a unified diff to update text value at the specified path.
+    ///
+    /// This is more efficient than replacing entire text values when only small
+    /// portions change. The diff is parsed and applied using Automerge's text
+    /// operations (splice_text) to preserve CRDT properties.
+    ///
+    /// Hunks are applied in order at the old-side line numbers in their
+    /// `@@` headers, and lines between hunks are kept as they are. A
+    /// `\ No newline at end of file` marker decides whether the result
+    /// ends in a newline; without one the original's trailing newline is
+    /// kept. Context and deleted lines must match the current text exactly.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the text field
+    /// * `diff` - Unified diff in git format
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("doc", "Hello World").unwrap();
+    ///
+    /// let diff = r#"--- a/doc
+    /// +++ b/doc
+    /// @@ -1 +1 @@
+    /// -Hello World
+    /// +Hello Rust
+    /// "#;
+    /// client.put_diff("doc", diff).unwrap();
+    ///
+    /// assert_eq!(client.get_text("doc").unwrap(), Some("Hello Rust".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path is invalid or doesn't exist
+    /// - The value at path is not text
+    /// - The diff cannot be parsed, its hunk counts don't add up, or its
+    ///   hunks overlap
+    /// - The diff cannot be applied to the current text
+    pub fn put_diff(&mut self, path: &str, diff: &str) -> Result<(), DiffError> {
+        let new_text = self.compute_diff_application(path, diff)?;
+        self.put_text(path, &new_text)?;
+        Ok(())
+    }
+
+    /// Apply a unified diff and return the raw change bytes.
+    pub fn put_diff_with_change(
+        &mut self,
+        path: &str,
+        diff: &str,
+    ) -> Result<Option<Vec<u8>>, DiffError> {
+        let new_text = self.compute_diff_application(path, diff)?;
+        Ok(self.put_text_with_change(path, &new_text)?)
     }
 
-    /// Retrieve a boolean value using a path (e.g., "flags.active", "flags\[0\]", or "$.flags.active").
-    pub fn get_bool(&self, path: &str) -> Result<Option<bool>, AutomergeError> {
-        let segments = parse_path(path)?;
+    /// Shared diff-application core for [`put_diff`] and
+    /// [`put_diff_with_change`] (audit #33 dedup). Returns the resulting text
+    /// after strictly applying the diff against the document's current value
+    /// at `path`. Audit #18: context-line and delete-line mismatches now
+    /// return a `DiffError` with the offending line numbers instead of
+    /// silently producing garbage.
+    fn compute_diff_application(
+        &self,
+        path: &str,
+        diff: &str,
+    ) -> Result<String, DiffError> {
+        let segments = parse_path(path)?;
+        if segments.is_empty() {
+            return Err(DiffError::Automerge(AutomergeError::Fail));
+        }
+
+        let current_text = self.get_text(path)?.ok_or(AutomergeError::Fail)?;
+        let current_lines: Vec<&str> = current_text.lines().collect();
+        let mut trailing_newline = current_text.ends_with('\n');
+
+        let hunks = parse_unified_diff(diff)?;
+
+        let mut new_lines = Vec::new();
+        let mut idx = 0;
+        for hunk in &hunks {
+            let start = hunk.old_start.unwrap_or(idx);
+            if start < idx {
+                return Err(DiffError::Malformed(format!(
+                    "hunk at line {} overlaps the previous hunk",
+                    start + 1
+                )));
+            }
+            if start > current_lines.len() {
+                return Err(DiffError::UnexpectedEof {
+                    line_num: start + 1,
+                    op: "hunk",
+                });
+            }
+            // Lines between hunks are kept verbatim.
+            new_lines.extend(current_lines[idx..start].iter().map(|l| l.to_string()));
+            idx = start;
+
+            for op in &hunk.ops {
+                match op {
+                    DiffOp::Context(line) => {
+                        if idx >= current_lines.len() {
+                            return Err(DiffError::UnexpectedEof {
+                                line_num: idx + 1,
+                                op: "context",
+                            });
+                        }
+                        let actual = current_lines[idx];
+                        if actual != line.as_str() {
+                            return Err(DiffError::ContextMismatch {
+                                line_num: idx + 1,
+                                expected: line.clone(),
+                                actual: actual.to_string(),
+                            });
+                        }
+                        new_lines.push(actual.to_string());
+                        idx += 1;
+                    }
+                    DiffOp::Delete(line) => {
+                        if idx >= current_lines.len() {
+                            return Err(DiffError::UnexpectedEof {
+                                line_num: idx + 1,
+                                op: "delete",
+                            });
+                        }
+                        let actual = current_lines[idx];
+                        if actual != line.as_str() {
+                            return Err(DiffError::DeleteMismatch {
+                                line_num: idx + 1,
+                                expected: line.clone(),
+                                actual: actual.to_string(),
+                            });
+                        }
+                        idx += 1;
+                    }
+                    DiffOp::Add(line) => {
+                        new_lines.push(line.clone());
+                    }
+                }
+            }
+
+            // The marker must sit on the old text's real last line, and
+            // only then may the diff change whether the text ends in '\n'.
+            if hunk.old_no_newline || hunk.new_no_newline {
+                if idx != current_lines.len()
+                    || (hunk.old_no_newline && current_text.ends_with('\n'))
+                {
+                    return Err(DiffError::NewlineMismatch { line_num: idx });
+                }
+                trailing_newline = !hunk.new_no_newline;
+            }
+        }
 
-        if segments.is_empty() {
-            return Ok(None);
+        // Append any trailing lines beyond the diff's last hunk verbatim.
+        while idx < current_lines.len() {
+            new_lines.push(current_lines[idx].to_string());
+            idx += 1;
         }
 
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = if parent_path.is_empty() {
-            ROOT
+        // Keep the original's trailing newline unless a no-newline marker
+        // at the end of the text said otherwise.
+        Ok(if trailing_newline && !new_lines.is_empty() {
+            new_lines.join("\n") + "\n"
         } else {
-            match navigate_path_read(&self.doc, parent_path)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
-        };
+            new_lines.join("\n")
+        })
+    }
 
-        if let Some((Value::Scalar(s), _)) =
-            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
-        {
-            if let ScalarValue::Boolean(b) = s.as_ref() {
-                return Ok(Some(*b));
-            }
-        }
-        Ok(None)
+    /// Insert a JSON value at the front of the list at `path`.
+    ///
+    /// The value is typed as in [`list_set_json`](Self::list_set_json):
+    /// strings, integers, doubles, booleans and null become scalars, and
+    /// objects and arrays become nested maps and lists, so a whole record
+    /// can be prepended in one change.
+    ///
+    /// Concurrent prepends to the same list all survive a merge. Automerge
+    /// orders elements inserted at the same position by their op ids, so
+    /// every replica ends up with the same order, but it is not the order
+    /// in which the writes happened in wall-clock time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("feed").unwrap();
+    /// client.prepend("feed", r#""first""#).unwrap();
+    /// client.prepend("feed", r#"{"event":"login"}"#).unwrap();
+    ///
+    /// assert_eq!(client.get_text("feed[0].event").unwrap(), Some("login".to_string()));
+    /// assert_eq!(client.get_text("feed[1]").unwrap(), Some("first".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list,
+    /// or `json` is not valid JSON.
+    pub fn prepend(&mut self, path: &str, json: &str) -> Result<(), AutomergeError> {
+        self.prepend_with_change(path, json).map(|_| ())
     }
 
-    /// Insert a boolean value and return the raw change bytes.
-    pub fn put_bool_with_change(
+    /// Prepend a JSON value to a list and return the raw change bytes.
+    pub fn prepend_with_change(
         &mut self,
         path: &str,
-        value: bool,
+        json: &str,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let json_value: JsonValue = serde_json::from_str(json).map_err(|_| AutomergeError::Fail)?;
         let segments = parse_path(path)?;
-        let mut tx = self.doc.transaction();
-
-        if segments.is_empty() {
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
             return Err(AutomergeError::Fail);
         }
 
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
-
-        put_value_to_parent(&mut tx, &parent_obj, &field_name[0], value)?;
+        let mut tx = self.doc.transaction();
+        let segment = PathSegment::Index(0);
+        put_json_to_parent(&mut tx, &json_value, &list_obj, &segment, true, 1)?;
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -859,21 +4787,81 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Insert a counter value using a path (e.g., "stats.views", "counters[0]", or "$.stats.views").
-    /// Creates intermediate maps as needed. Array indices must already exist.
+    /// Creates a new empty list at the specified path.
     ///
-    /// Counters are CRDT values that support increment operations with proper
-    /// conflict resolution across distributed systems.
-    pub fn put_counter(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
+    /// Creates intermediate maps as needed. The final segment must be a map key.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the list should be created
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("users").unwrap();
+    /// client.create_list("data.items").unwrap();
+    ///
+    /// assert_eq!(client.list_len("users").unwrap(), Some(0));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is empty or the final segment is an array index.
+    pub fn create_list(&mut self, path: &str) -> Result<(), AutomergeError> {
         // Audit #32: delegate to the `_with_change` variant.
-        self.put_counter_with_change(path, value).map(|_| ())
+        self.create_list_with_change(path).map(|_| ())
     }
 
-    /// Insert a counter value and return the raw change bytes.
-    pub fn put_counter_with_change(
+    /// Create a new empty list and return the raw change bytes.
+    pub fn create_list_with_change(
         &mut self,
         path: &str,
-        value: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.create_list_seeded_with_change(path, &[])
+    }
+
+    /// Creates a new list at the specified path, pre-filled with text values.
+    ///
+    /// The list and its elements are written in a single transaction, so
+    /// seeding produces one change rather than one per element. Seed values
+    /// are always stored as text, exactly as `append_text` would store them;
+    /// no attempt is made to guess numeric or boolean types.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list_seeded("tags", &["red", "green", "blue"]).unwrap();
+    ///
+    /// assert_eq!(client.list_len("tags").unwrap(), Some(3));
+    /// assert_eq!(client.get_text("tags[1]").unwrap(), Some("green".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is empty, the final segment is an array
+    /// index, or an index in the path names a missing element or one that
+    /// is not a map.
+    pub fn create_list_seeded(
+        &mut self,
+        path: &str,
+        values: &[&str],
+    ) -> Result<(), AutomergeError> {
+        self.create_list_seeded_with_change(path, values)
+            .map(|_| ())
+    }
+
+    /// Create a new list pre-filled with text values and return the raw
+    /// change bytes.
+    pub fn create_list_seeded_with_change(
+        &mut self,
+        path: &str,
+        values: &[&str],
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
         let mut tx = self.doc.transaction();
@@ -883,15 +4871,23 @@ impl RedisAutomergeClient {
         }
 
         let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        // Existing list elements are followed (`records[0].children`), but
+        // the element must be a map to hold a named list.
         let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+        if tx.object_type(&parent_obj)? != automerge::ObjType::Map {
+            return Err(AutomergeError::Fail);
+        }
 
-        // Put counter value
         match &field_name[0] {
             PathSegment::Key(key) => {
-                tx.put(&parent_obj, key.as_str(), ScalarValue::Counter(value.into()))?;
+                let list_obj =
+                    tx.put_object(&parent_obj, key.as_str(), automerge::ObjType::List)?;
+                for (i, value) in values.iter().enumerate() {
+                    tx.insert(&list_obj, i, *value)?;
+                }
             }
-            PathSegment::Index(idx) => {
-                tx.put(&parent_obj, *idx, ScalarValue::Counter(value.into()))?;
+            PathSegment::Index(_) => {
+                return Err(AutomergeError::Fail); // Cannot create list at index
             }
         }
 
@@ -906,258 +4902,362 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Retrieve a counter value using a path (e.g., "stats.views", "counters[0]", or "$.stats.views").
-    /// Returns the current counter value as an i64.
-    pub fn get_counter(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
-        let segments = parse_path(path)?;
-
-        if segments.is_empty() {
-            return Ok(None);
-        }
+    /// Renames a single key of the map at `parent_path`, keeping its value.
+    ///
+    /// The value stored under `old_key` is written under `new_key` in the
+    /// same map and `old_key` is deleted, all in one transaction. Scalars
+    /// are moved as-is. Container values (maps, lists, text) are
+    /// deep-copied, so the renamed value is a *new* Automerge object:
+    /// concurrent edits that other replicas make to the object under the
+    /// old key will not follow it to the new key.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent_path` - Path to the map holding the key (empty for the root)
+    /// * `old_key` - Existing key to rename
+    /// * `new_key` - Key to move the value to
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.fist_name", "Alice").unwrap();
+    /// client.rename_key("user", "fist_name", "first_name").unwrap();
+    ///
+    /// assert_eq!(client.get_text("user.first_name").unwrap(), Some("Alice".to_string()));
+    /// assert_eq!(client.get_text("user.fist_name").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `parent_path` does not exist or is not a map
+    /// - `old_key` is absent
+    /// - `new_key` already exists
+    pub fn rename_key(
+        &mut self,
+        parent_path: &str,
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<(), AutomergeError> {
+        self.rename_key_with_change(parent_path, old_key, new_key)
+            .map(|_| ())
+    }
 
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = if parent_path.is_empty() {
+    /// Rename a map key and return the raw change bytes.
+    pub fn rename_key_with_change(
+        &mut self,
+        parent_path: &str,
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let segments = parse_path(parent_path)?;
+        let parent_obj = if segments.is_empty() {
             ROOT
         } else {
-            match navigate_path_read(&self.doc, parent_path)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&parent_obj)? != automerge::ObjType::Map {
+            return Err(AutomergeError::Fail);
+        }
+        if old_key == new_key || self.doc.get(&parent_obj, new_key)?.is_some() {
+            return Err(AutomergeError::Fail);
+        }
+        let (value, src) = match self.doc.get(&parent_obj, old_key)? {
+            Some((value, src)) => (value.into_owned(), src),
+            None => return Err(AutomergeError::Fail),
         };
 
-        if let Some((Value::Scalar(s), _)) =
-            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
-        {
-            if let ScalarValue::Counter(c) = s.as_ref() {
-                return Ok(Some(i64::from(c)));
+        let mut tx = self.doc.transaction();
+        let segment = PathSegment::Key(new_key.to_string());
+        copy_value_to_parent(
+            None::<&Automerge>,
+            &mut tx,
+            &value,
+            &src,
+            &parent_obj,
+            &segment,
+            false,
+        )?;
+        tx.delete(&parent_obj, old_key)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
             }
         }
+
         Ok(None)
     }
 
-    /// Get a value with type information from the specified path.
+    /// Delete one or more paths in a single transaction.
     ///
-    /// This method extracts values preserving their Automerge types, which is
-    /// useful for building JSON index documents with proper type representation.
+    /// Paths are deleted in the order given, each resolved against the
+    /// document as left by the previous deletions, so deleting a parent and
+    /// then one of its children is fine: the child no longer exists and
+    /// counts as 0. A path whose parent or final key/index is missing also
+    /// counts as 0 rather than failing. List indices shift after each
+    /// deletion, exactly as they would across separate commands.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path to the value
+    /// * `paths` - Paths to delete, in order
     ///
     /// # Returns
     ///
-    /// Returns `Some(TypedValue)` if the path exists, `None` otherwise.
-    pub fn get_typed_value(&self, path: &str) -> Result<Option<TypedValue>, AutomergeError> {
-        let segments = parse_path(path)?;
-
-        if segments.is_empty() {
-            return Ok(None);
-        }
-
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = if parent_path.is_empty() {
-            ROOT
-        } else {
-            match navigate_path_read(&self.doc, parent_path)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
-        };
-
-        match get_value_from_parent(&self.doc, &parent_obj, &field_name[0])? {
-            Some((Value::Scalar(s), _)) => {
-                let typed_val = match s.as_ref() {
-                    ScalarValue::Str(text) => TypedValue::Text(text.to_string()),
-                    ScalarValue::Int(i) => TypedValue::Int(*i),
-                    ScalarValue::F64(f) => TypedValue::Double(*f),
-                    ScalarValue::Boolean(b) => TypedValue::Bool(*b),
-                    ScalarValue::Timestamp(ts) => TypedValue::Timestamp(*ts),
-                    ScalarValue::Counter(c) => TypedValue::Counter(i64::from(c)),
-                    ScalarValue::Null => TypedValue::Null,
-                    _ => TypedValue::Null,
-                };
-                Ok(Some(typed_val))
-            }
-            Some((Value::Object(obj_type), obj_id)) => {
-                // Handle Text objects
-                if obj_type == automerge::ObjType::Text {
-                    let text = self.doc.text(&obj_id)?;
-                    return Ok(Some(TypedValue::Text(text)));
-                }
-
-                // Handle List objects - return as Array
-                if obj_type == automerge::ObjType::List {
-                    let mut arr = Vec::new();
-                    let len = self.doc.length(&obj_id);
-                    for i in 0..len {
-                        if let Some((value, value_obj_id)) = self.doc.get(&obj_id, i)? {
-                            if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
-                                arr.push(typed_val);
-                            }
-                        }
-                    }
-                    return Ok(Some(TypedValue::Array(arr)));
-                }
-
-                // Handle Map objects
-                if obj_type == automerge::ObjType::Map {
-                    let mut map = std::collections::HashMap::new();
-                    for key in self.doc.keys(&obj_id) {
-                        if let Some((value, value_obj_id)) = self.doc.get(&obj_id, &key)? {
-                            if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
-                                map.insert(key, typed_val);
-                            }
-                        }
-                    }
-                    return Ok(Some(TypedValue::Object(map)));
-                }
+    /// The number of values actually removed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.put_int("user.age", 30).unwrap();
+    ///
+    /// let removed = client.delete_paths(&["user.name", "user.email"]).unwrap();
+    /// assert_eq!(removed, 1);
+    /// assert_eq!(client.get_text("user.name").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any path is malformed or empty (the root cannot
+    /// be deleted). Nothing is deleted in that case.
+    pub fn delete_paths(&mut self, paths: &[&str]) -> Result<usize, AutomergeError> {
+        self.delete_paths_with_change(paths)
+            .map(|(removed, _)| removed)
+    }
 
-                Ok(None)
+    /// Delete one or more paths and return the count removed along with the
+    /// raw change bytes (`None` if nothing was removed).
+    pub fn delete_paths_with_change(
+        &mut self,
+        paths: &[&str],
+    ) -> Result<(usize, Option<Vec<u8>>), AutomergeError> {
+        let mut parsed = Vec::with_capacity(paths.len());
+        for path in paths {
+            let mut segments = parse_path(path)?;
+            match segments.pop() {
+                Some(last) => parsed.push((segments, last)),
+                None => return Err(AutomergeError::Fail),
             }
-            None => Ok(None),
         }
-    }
 
-    /// Helper method to convert Automerge Value to TypedValue
-    fn value_to_typed(
-        &self,
-        value: &Value,
-        obj_id: &ObjId,
-    ) -> Result<Option<TypedValue>, AutomergeError> {
-        match value {
-            Value::Scalar(s) => {
-                let typed_val = match s.as_ref() {
-                    ScalarValue::Str(text) => TypedValue::Text(text.to_string()),
-                    ScalarValue::Int(i) => TypedValue::Int(*i),
-                    ScalarValue::F64(f) => TypedValue::Double(*f),
-                    ScalarValue::Boolean(b) => TypedValue::Bool(*b),
-                    ScalarValue::Timestamp(ts) => TypedValue::Timestamp(*ts),
-                    ScalarValue::Counter(c) => TypedValue::Counter(i64::from(c)),
-                    ScalarValue::Null => TypedValue::Null,
-                    _ => TypedValue::Null,
-                };
-                Ok(Some(typed_val))
+        let mut tx = self.doc.transaction();
+        let mut removed = 0;
+        for (parent_segments, last) in &parsed {
+            if delete_at(&mut tx, parent_segments, last)? {
+                removed += 1;
             }
-            Value::Object(obj_type) => {
-                // Handle Text objects
-                if *obj_type == automerge::ObjType::Text {
-                    let text = self.doc.text(obj_id)?;
-                    return Ok(Some(TypedValue::Text(text)));
-                }
-
-                // Handle List objects
-                if *obj_type == automerge::ObjType::List {
-                    let mut arr = Vec::new();
-                    let len = self.doc.length(obj_id);
-                    for i in 0..len {
-                        if let Some((val, val_obj_id)) = self.doc.get(obj_id, i)? {
-                            if let Some(typed_val) = self.value_to_typed(&val, &val_obj_id)? {
-                                arr.push(typed_val);
-                            }
-                        }
-                    }
-                    return Ok(Some(TypedValue::Array(arr)));
-                }
-
-                // Handle Map objects
-                if *obj_type == automerge::ObjType::Map {
-                    let mut map = std::collections::HashMap::new();
-                    for key in self.doc.keys(obj_id) {
-                        if let Some((val, val_obj_id)) = self.doc.get(obj_id, &key)? {
-                            if let Some(typed_val) = self.value_to_typed(&val, &val_obj_id)? {
-                                map.insert(key, typed_val);
-                            }
-                        }
-                    }
-                    return Ok(Some(TypedValue::Object(map)));
-                }
+        }
+        if removed == 0 {
+            tx.rollback();
+            return Ok((0, None));
+        }
+        let (hash, _patch) = tx.commit();
 
-                Ok(None)
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok((removed, Some(change.raw_bytes().to_vec())));
             }
         }
+
+        Ok((removed, None))
     }
 
-    /// Get all values from a list at the specified path.
+    /// Appends a text value to the end of a list at the specified path.
+    ///
+    /// The list must already exist at the given path.
     ///
     /// # Arguments
     ///
     /// * `path` - Path to the list
+    /// * `value` - Text value to append
     ///
-    /// # Returns
+    /// # Examples
     ///
-    /// Returns `Some(Vec<TypedValue>)` if the path points to a list, `None` otherwise.
-    pub fn get_list_values(&self, path: &str) -> Result<Option<Vec<TypedValue>>, AutomergeError> {
-        let segments = parse_path(path)?;
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("users").unwrap();
+    /// client.append_text("users", "Alice").unwrap();
+    /// client.append_text("users", "Bob").unwrap();
+    ///
+    /// assert_eq!(client.get_text("users[0]").unwrap(), Some("Alice".to_string()));
+    /// assert_eq!(client.list_len("users").unwrap(), Some(2));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn append_text(&mut self, path: &str, value: &str) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.append_text_with_change(path, value).map(|_| ())
+    }
 
-        let list_obj = if segments.is_empty() {
-            ROOT
-        } else {
-            match navigate_path_read(&self.doc, &segments)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
-        };
+    /// Append a text value to a list and return the raw change bytes.
+    pub fn append_text_with_change(
+        &mut self,
+        path: &str,
+        value: &str,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.append_value_with_change(path, value, false)
+    }
 
-        // Check if it's a list
-        let obj_type = self.doc.object_type(&list_obj)?;
-        if obj_type == automerge::ObjType::List {
-            let mut values = Vec::new();
-            let len = self.doc.length(&list_obj);
+    /// Append an integer value to a list at the specified path.
+    pub fn append_int(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.append_int_with_change(path, value).map(|_| ())
+    }
 
-            for i in 0..len {
-                if let Some((value, value_obj_id)) = self.doc.get(&list_obj, i)? {
-                    if let Some(typed_val) = self.value_to_typed(&value, &value_obj_id)? {
-                        values.push(typed_val);
-                    }
-                }
-            }
+    /// Append an integer value to a list and return the raw change bytes.
+    pub fn append_int_with_change(
+        &mut self,
+        path: &str,
+        value: i64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.append_value_with_change(path, value, false)
+    }
 
-            return Ok(Some(values));
-        }
+    /// Append a double value to a list at the specified path.
+    pub fn append_double(&mut self, path: &str, value: f64) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.append_double_with_change(path, value).map(|_| ())
+    }
 
-        Ok(None)
+    /// Append a double value to a list and return the raw change bytes.
+    pub fn append_double_with_change(
+        &mut self,
+        path: &str,
+        value: f64,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.append_value_with_change(path, value, false)
     }
 
-    /// Get all keys from a map at the specified path.
+    /// Append a boolean value to a list at the specified path.
+    pub fn append_bool(&mut self, path: &str, value: bool) -> Result<(), AutomergeError> {
+        // Audit #32: delegate to the `_with_change` variant.
+        self.append_bool_with_change(path, value).map(|_| ())
+    }
+
+    /// Append a boolean value to a list and return the raw change bytes.
+    pub fn append_bool_with_change(
+        &mut self,
+        path: &str,
+        value: bool,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.append_value_with_change(path, value, false)
+    }
+
+    /// Append a scalar value to a list and return the raw change bytes.
     ///
-    /// # Arguments
+    /// This backs the typed `append_*` methods. When `create` is true and
+    /// nothing exists at `path`, an empty list is created there (along with
+    /// any missing parent maps) in the same transaction as the append, so
+    /// the whole operation produces a single change. A path that exists but
+    /// holds something other than a list is still an error.
     ///
-    /// * `path` - Path to the map
+    /// # Examples
     ///
-    /// # Returns
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
     ///
-    /// Returns `Some(Vec<String>)` if the path points to a map, `None` otherwise.
-    pub fn get_map_keys(&self, path: &str) -> Result<Option<Vec<String>>, AutomergeError> {
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.append_value_with_change("tags", "first", true).unwrap();
+    /// client.append_value_with_change("tags", "second", true).unwrap();
+    ///
+    /// assert_eq!(client.list_len("tags").unwrap(), Some(2));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't point to a list (and `create` is
+    /// false or the path is occupied), or the final segment is an index.
+    pub fn append_value_with_change<V: Into<ScalarValue>>(
+        &mut self,
+        path: &str,
+        value: V,
+        create: bool,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.append_value_with_index(path, value, create)
+            .map(|(_, change)| change)
+    }
+
+    /// Like [`append_value_with_change`](Self::append_value_with_change),
+    /// but also returns the index the value was appended at.
+    pub fn append_value_with_index<V: Into<ScalarValue>>(
+        &mut self,
+        path: &str,
+        value: V,
+        create: bool,
+    ) -> Result<(usize, Option<Vec<u8>>), AutomergeError> {
         let segments = parse_path(path)?;
 
-        let map_obj = if segments.is_empty() {
-            ROOT
+        // Navigate before creating transaction
+        let existing = if segments.is_empty() {
+            Some(ROOT)
         } else {
-            match navigate_path_read(&self.doc, &segments)? {
-                Some(obj) => obj,
-                None => return Ok(None),
+            navigate_path_read(&self.doc, &segments)?
+        };
+
+        let mut tx = self.doc.transaction();
+        let list_obj = match existing {
+            Some(obj) => {
+                if create && tx.object_type(&obj)? != automerge::ObjType::List {
+                    return Err(AutomergeError::Fail);
+                }
+                obj
+            }
+            None if create => {
+                let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+                let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
+                match &field_name[0] {
+                    // Only create into an empty slot; a scalar here is not a list.
+                    PathSegment::Key(key) if tx.get(&parent_obj, key.as_str())?.is_none() => {
+                        tx.put_object(&parent_obj, key.as_str(), automerge::ObjType::List)?
+                    }
+                    _ => return Err(AutomergeError::Fail),
+                }
             }
+            None => return Err(AutomergeError::Fail),
         };
 
-        // Check if it's a map
-        let obj_type = self.doc.object_type(&map_obj)?;
-        if obj_type == automerge::ObjType::Map {
-            let keys: Vec<String> = self.doc.keys(&map_obj).collect();
-            return Ok(Some(keys));
+        let index = tx.length(&list_obj);
+        tx.insert(&list_obj, index, value)?;
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok((index, Some(change.raw_bytes().to_vec())));
+            }
         }
 
-        Ok(None)
+        Ok((index, None))
     }
 
-    /// Increment a counter at the specified path by the given delta.
+    /// Removes duplicate scalar elements from the list at `path`.
     ///
-    /// This uses Automerge's CRDT counter increment operation, which properly
-    /// merges concurrent increments from different replicas.
+    /// The first occurrence of each distinct value is kept and relative order
+    /// is preserved. Values are compared as
+    /// [`list_distinct_count`](Self::list_distinct_count) compares them: only
+    /// when both their type and value match, so `1`, `1.0` and `"1"` are all
+    /// kept, while `0.0` and `-0.0` are one value and so are equal NaNs.
+    ///
+    /// This *rewrites* the list: every element is deleted and the deduplicated
+    /// set is re-inserted in one transaction. It is not merge-friendly —
+    /// elements that other replicas insert or edit concurrently are ordered
+    /// relative to the old elements, not the rewritten ones. If the list
+    /// already holds no duplicates nothing is written.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path to the counter field
-    /// * `delta` - Amount to increment (can be negative to decrement)
+    /// * `path` - Path to the list
     ///
     /// # Examples
     ///
@@ -1165,53 +5265,72 @@ impl RedisAutomergeClient {
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
     /// let mut client = RedisAutomergeClient::new();
-    /// client.put_counter("views", 0).unwrap();
-    /// client.inc_counter("views", 1).unwrap();
-    /// client.inc_counter("views", 5).unwrap();
+    /// client.create_list("tags").unwrap();
+    /// client.append_text("tags", "red").unwrap();
+    /// client.append_text("tags", "blue").unwrap();
+    /// client.append_text("tags", "red").unwrap();
+    /// client.list_unique("tags").unwrap();
     ///
-    /// assert_eq!(client.get_counter("views").unwrap(), Some(6));
+    /// assert_eq!(client.list_len("tags").unwrap(), Some(2));
     /// ```
-    pub fn inc_counter(&mut self, path: &str, delta: i64) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.inc_counter_with_change(path, delta).map(|_| ())
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist, doesn't point to a list, or
+    /// the list contains a nested object (map, list or text). Lists holding
+    /// objects are left untouched.
+    pub fn list_unique(&mut self, path: &str) -> Result<(), AutomergeError> {
+        self.list_unique_with_change(path).map(|_| ())
     }
 
-    /// Increment a counter and return the raw change bytes.
-    pub fn inc_counter_with_change(
+    /// Deduplicate a list of scalars and return the raw change bytes.
+    pub fn list_unique_with_change(
         &mut self,
         path: &str,
-        delta: i64,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
-
-        if segments.is_empty() {
-            return Err(AutomergeError::Fail);
-        }
-
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-
-        // Get parent object (don't create it if it doesn't exist for increment)
-        let parent_obj = if parent_path.is_empty() {
+        let list_obj = if segments.is_empty() {
             ROOT
         } else {
-            match navigate_path_read(&self.doc, parent_path)? {
-                Some(obj) => obj,
-                None => return Err(AutomergeError::Fail),
-            }
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
         };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
+        }
 
-        let mut tx = self.doc.transaction();
-
-        // Increment the counter
-        match &field_name[0] {
-            PathSegment::Key(key) => {
-                tx.increment(&parent_obj, key.as_str(), delta)?;
-            }
-            PathSegment::Index(idx) => {
-                tx.increment(&parent_obj, *idx, delta)?;
+        let len = self.doc.length(&list_obj);
+        let mut seen = std::collections::HashSet::new();
+        let mut unique: Vec<Value<'static>> = Vec::new();
+        for i in 0..len {
+            match self.doc.get(&list_obj, i)? {
+                Some((Value::Scalar(s), _)) => {
+                    if seen.insert(scalar_identity(&s)) {
+                        unique.push(Value::Scalar(s).into_owned());
+                    }
+                }
+                _ => return Err(AutomergeError::Fail),
             }
         }
+        if unique.len() == len {
+            return Ok(None);
+        }
 
+        let mut tx = self.doc.transaction();
+        for i in (0..len).rev() {
+            tx.delete(&list_obj, i)?;
+        }
+        for (i, value) in unique.iter().enumerate() {
+            let segment = PathSegment::Index(i);
+            copy_value_to_parent(
+                None::<&Automerge>,
+                &mut tx,
+                value,
+                &list_obj,
+                &list_obj,
+                &segment,
+                true,
+            )?;
+        }
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -1223,16 +5342,17 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Insert a timestamp value using a path (e.g., "event.created_at", "timestamps[0]", or "$.event.timestamp").
-    /// Creates intermediate maps as needed. Array indices must already exist.
-    ///
-    /// Timestamps are stored as i64 values representing milliseconds since Unix epoch (UTC).
-    /// They will be rendered as ISO 8601 UTC datetime strings when exported to JSON.
+    /// Sorts the scalar elements of the list at `path` in their natural
+    /// order, descending when `descending` is true.
     ///
-    /// # Arguments
+    /// Numbers (integers, unsigned integers, doubles and counters) sort
+    /// numerically, strings by their UTF-8 bytes, timestamps by time and
+    /// booleans `false` first. The sort is stable, so equal elements keep
+    /// their relative order.
     ///
-    /// * `path` - Path to the field
-    /// * `value` - Unix timestamp in milliseconds
+    /// Like [`list_unique`](Self::list_unique), this *rewrites* the list in
+    /// one transaction and is not merge-friendly. If the list is already in
+    /// order nothing is written.
     ///
     /// # Examples
     ///
@@ -1240,40 +5360,87 @@ impl RedisAutomergeClient {
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
     /// let mut client = RedisAutomergeClient::new();
-    /// // Set timestamp to 2024-01-01 00:00:00 UTC (1704067200000 milliseconds)
-    /// client.put_timestamp("created_at", 1704067200000).unwrap();
+    /// client.create_list("scores").unwrap();
+    /// client.append_int("scores", 3).unwrap();
+    /// client.append_double("scores", 1.5).unwrap();
+    /// client.append_int("scores", 2).unwrap();
+    /// client.list_sort("scores", false).unwrap();
+    ///
+    /// assert_eq!(client.get_double("scores[0]").unwrap(), Some(1.5));
+    /// assert_eq!(client.get_int("scores[2]").unwrap(), Some(3));
     /// ```
-    pub fn put_timestamp(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.put_timestamp_with_change(path, value).map(|_| ())
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist, doesn't point to a list,
+    /// or the list mixes kinds of values (say numbers and strings) or holds
+    /// nulls, byte strings or nested objects. The list is left untouched.
+    pub fn list_sort(&mut self, path: &str, descending: bool) -> Result<(), AutomergeError> {
+        self.list_sort_with_change(path, descending).map(|_| ())
     }
 
-    /// Insert a timestamp value and return the raw change bytes.
-    pub fn put_timestamp_with_change(
+    /// Sort a list of scalars and return the raw change bytes.
+    pub fn list_sort_with_change(
         &mut self,
         path: &str,
-        value: i64,
+        descending: bool,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
-        let mut tx = self.doc.transaction();
-
-        if segments.is_empty() {
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
             return Err(AutomergeError::Fail);
         }
 
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
-
-        // Put timestamp value
-        match &field_name[0] {
-            PathSegment::Key(key) => {
-                tx.put(&parent_obj, key.as_str(), ScalarValue::Timestamp(value))?;
+        let len = self.doc.length(&list_obj);
+        let mut values: Vec<ScalarValue> = Vec::with_capacity(len);
+        let mut kind = None;
+        for i in 0..len {
+            match self.doc.get(&list_obj, i)? {
+                Some((Value::Scalar(s), _)) => {
+                    let this = sort_kind(&s).ok_or(AutomergeError::Fail)?;
+                    if *kind.get_or_insert(this) != this {
+                        return Err(AutomergeError::Fail);
+                    }
+                    values.push(s.into_owned());
+                }
+                _ => return Err(AutomergeError::Fail),
             }
-            PathSegment::Index(idx) => {
-                tx.put(&parent_obj, *idx, ScalarValue::Timestamp(value))?;
+        }
+
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| {
+            let order = compare_scalars(a, b);
+            if descending {
+                order.reverse()
+            } else {
+                order
             }
+        });
+        if sorted == values {
+            return Ok(None);
         }
 
+        let mut tx = self.doc.transaction();
+        for i in (0..len).rev() {
+            tx.delete(&list_obj, i)?;
+        }
+        for (i, value) in sorted.into_iter().enumerate() {
+            let value = Value::Scalar(std::borrow::Cow::Owned(value));
+            let segment = PathSegment::Index(i);
+            copy_value_to_parent(
+                None::<&Automerge>,
+                &mut tx,
+                &value,
+                &list_obj,
+                &list_obj,
+                &segment,
+                true,
+            )?;
+        }
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -1285,45 +5452,12 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Retrieve a timestamp value using a path (e.g., "event.created_at", "timestamps[0]", or "$.event.timestamp").
-    /// Returns the timestamp as an i64 (milliseconds since Unix epoch).
-    pub fn get_timestamp(&self, path: &str) -> Result<Option<i64>, AutomergeError> {
-        let segments = parse_path(path)?;
-
-        if segments.is_empty() {
-            return Ok(None);
-        }
-
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = if parent_path.is_empty() {
-            ROOT
-        } else {
-            match navigate_path_read(&self.doc, parent_path)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
-        };
-
-        if let Some((Value::Scalar(s), _)) =
-            get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?
-        {
-            if let ScalarValue::Timestamp(ts) = s.as_ref() {
-                return Ok(Some(*ts));
-            }
-        }
-        Ok(None)
-    }
-
-    /// Apply a unified diff to update text value at the specified path.
-    ///
-    /// This is more efficient than replacing entire text values when only small
-    /// portions change. The diff is parsed and applied using Automerge's text
-    /// operations (splice_text) to preserve CRDT properties.
-    ///
-    /// # Arguments
+    /// Uppercases every string element of the list at `path`.
     ///
-    /// * `path` - Path to the text field
-    /// * `diff` - Unified diff in git format
+    /// Each string scalar is overwritten in place, so the list keeps its
+    /// length and other element types are left untouched. This is not
+    /// merge-friendly: overwriting an element conflicts with any concurrent
+    /// write to it, and whichever write wins the conflict is kept as-is.
     ///
     /// # Examples
     ///
@@ -1331,129 +5465,110 @@ impl RedisAutomergeClient {
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
     /// let mut client = RedisAutomergeClient::new();
-    /// client.put_text("doc", "Hello World").unwrap();
-    ///
-    /// let diff = r#"--- a/doc
-    /// +++ b/doc
-    /// @@ -1 +1 @@
-    /// -Hello World
-    /// +Hello Rust
-    /// "#;
-    /// client.put_diff("doc", diff).unwrap();
+    /// client.create_list("tags").unwrap();
+    /// client.append_text("tags", "red").unwrap();
+    /// client.append_int("tags", 7).unwrap();
+    /// client.list_upper("tags").unwrap();
     ///
-    /// assert_eq!(client.get_text("doc").unwrap(), Some("Hello Rust".to_string()));
+    /// assert_eq!(client.get_text("tags[0]").unwrap(), Some("RED".to_string()));
+    /// assert_eq!(client.get_int("tags[1]").unwrap(), Some(7));
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The path is invalid or doesn't exist
-    /// - The value at path is not text
-    /// - The diff cannot be parsed
-    /// - The diff cannot be applied to the current text
-    pub fn put_diff(&mut self, path: &str, diff: &str) -> Result<(), DiffError> {
-        let new_text = self.compute_diff_application(path, diff)?;
-        self.put_text(path, &new_text)?;
-        Ok(())
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_upper(&mut self, path: &str) -> Result<(), AutomergeError> {
+        self.list_upper_with_change(path).map(|_| ())
     }
 
-    /// Apply a unified diff and return the raw change bytes.
-    pub fn put_diff_with_change(
+    /// Uppercase a list's string elements and return the raw change bytes.
+    pub fn list_upper_with_change(
         &mut self,
         path: &str,
-        diff: &str,
-    ) -> Result<Option<Vec<u8>>, DiffError> {
-        let new_text = self.compute_diff_application(path, diff)?;
-        Ok(self.put_text_with_change(path, &new_text)?)
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.list_map_strings_with_change(path, str::to_uppercase)
     }
 
-    /// Shared diff-application core for [`put_diff`] and
-    /// [`put_diff_with_change`] (audit #33 dedup). Returns the resulting text
-    /// after strictly applying the diff against the document's current value
-    /// at `path`. Audit #18: context-line and delete-line mismatches now
-    /// return a `DiffError` with the offending line numbers instead of
-    /// silently producing garbage.
-    fn compute_diff_application(
-        &self,
+    /// Lowercases every string element of the list at `path`.
+    ///
+    /// The counterpart of [`list_upper`](Self::list_upper), with the same
+    /// in-place, not merge-friendly rewrite.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path doesn't exist or doesn't point to a list.
+    pub fn list_lower(&mut self, path: &str) -> Result<(), AutomergeError> {
+        self.list_lower_with_change(path).map(|_| ())
+    }
+
+    /// Lowercase a list's string elements and return the raw change bytes.
+    pub fn list_lower_with_change(
+        &mut self,
         path: &str,
-        diff: &str,
-    ) -> Result<String, DiffError> {
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.list_map_strings_with_change(path, str::to_lowercase)
+    }
+
+    /// Overwrite each string scalar in a list with `f` applied to it, in one
+    /// transaction. Elements `f` leaves unchanged are not written, and
+    /// nothing is committed if no element changes.
+    fn list_map_strings_with_change(
+        &mut self,
+        path: &str,
+        f: fn(&str) -> String,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
         let segments = parse_path(path)?;
-        if segments.is_empty() {
-            return Err(DiffError::Automerge(AutomergeError::Fail));
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Err(AutomergeError::Fail);
         }
 
-        let current_text = self.get_text(path)?.ok_or(AutomergeError::Fail)?;
-        let current_lines: Vec<&str> = current_text.lines().collect();
-
-        let ops = parse_unified_diff(diff)?;
-
-        let mut new_lines = Vec::new();
-        let mut idx = 0;
-        for op in &ops {
-            match op {
-                DiffOp::Context(line) => {
-                    if idx >= current_lines.len() {
-                        return Err(DiffError::UnexpectedEof {
-                            line_num: idx + 1,
-                            op: "context",
-                        });
-                    }
-                    let actual = current_lines[idx];
-                    if actual != line.as_str() {
-                        return Err(DiffError::ContextMismatch {
-                            line_num: idx + 1,
-                            expected: line.clone(),
-                            actual: actual.to_string(),
-                        });
+        let mut updates = Vec::new();
+        for i in 0..self.doc.length(&list_obj) {
+            if let Some((Value::Scalar(v), _)) = self.doc.get(&list_obj, i)? {
+                if let ScalarValue::Str(s) = v.as_ref() {
+                    let mapped = f(s);
+                    if mapped != s.as_str() {
+                        updates.push((i, mapped));
                     }
-                    new_lines.push(actual.to_string());
-                    idx += 1;
-                }
-                DiffOp::Delete(line) => {
-                    if idx >= current_lines.len() {
-                        return Err(DiffError::UnexpectedEof {
-                            line_num: idx + 1,
-                            op: "delete",
-                        });
-                    }
-                    let actual = current_lines[idx];
-                    if actual != line.as_str() {
-                        return Err(DiffError::DeleteMismatch {
-                            line_num: idx + 1,
-                            expected: line.clone(),
-                            actual: actual.to_string(),
-                        });
-                    }
-                    idx += 1;
-                }
-                DiffOp::Add(line) => {
-                    new_lines.push(line.clone());
                 }
             }
         }
+        if updates.is_empty() {
+            return Ok(None);
+        }
 
-        // Append any trailing lines beyond the diff's last hunk verbatim.
-        while idx < current_lines.len() {
-            new_lines.push(current_lines[idx].to_string());
-            idx += 1;
+        let mut tx = self.doc.transaction();
+        for (i, value) in updates {
+            tx.put(&list_obj, i, value)?;
+        }
+        let (hash, _patch) = tx.commit();
+
+        if let Some(h) = hash {
+            if let Some(change) = self.doc.get_change_by_hash(&h) {
+                return Ok(Some(change.raw_bytes().to_vec()));
+            }
         }
 
-        // Preserve the trailing newline iff the original text had one.
-        Ok(if current_text.ends_with('\n') {
-            new_lines.join("\n") + "\n"
-        } else {
-            new_lines.join("\n")
-        })
+        Ok(None)
     }
 
-    /// Creates a new empty list at the specified path.
+    /// Replace a list element with a JSON value.
     ///
-    /// Creates intermediate maps as needed. The final segment must be a map key.
+    /// `json` may be an object, array or scalar; objects and arrays are
+    /// materialized as a new map or list subtree in one transaction, so a
+    /// whole record in a list of records can be swapped at once. The old
+    /// element is overwritten, including any nested objects it held.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path where the list should be created
+    /// * `path` - Path to the list
+    /// * `index` - Position of the element to replace
+    /// * `json` - JSON text of the new element
     ///
     /// # Examples
     ///
@@ -1462,43 +5577,53 @@ impl RedisAutomergeClient {
     ///
     /// let mut client = RedisAutomergeClient::new();
     /// client.create_list("users").unwrap();
-    /// client.create_list("data.items").unwrap();
+    /// client.append_text("users", "placeholder").unwrap();
+    /// client
+    ///     .list_set_json("users", 0, r#"{"name":"Alice","tags":["admin"]}"#)
+    ///     .unwrap();
     ///
-    /// assert_eq!(client.list_len("users").unwrap(), Some(0));
+    /// assert_eq!(client.get_text("users[0].name").unwrap(), Some("Alice".to_string()));
+    /// assert_eq!(client.get_text("users[0].tags[0]").unwrap(), Some("admin".to_string()));
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if the path is empty or the final segment is an array index.
-    pub fn create_list(&mut self, path: &str) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.create_list_with_change(path).map(|_| ())
+    /// Returns an error if the path doesn't exist or doesn't point to a list,
+    /// `index` is out of range, or `json` is not valid JSON.
+    pub fn list_set_json(
+        &mut self,
+        path: &str,
+        index: usize,
+        json: &str,
+    ) -> Result<(), AutomergeError> {
+        self.list_set_json_with_change(path, index, json)
+            .map(|_| ())
     }
 
-    /// Create a new empty list and return the raw change bytes.
-    pub fn create_list_with_change(
+    /// Replace a list element with a JSON value and return the raw change bytes.
+    pub fn list_set_json_with_change(
         &mut self,
         path: &str,
+        index: usize,
+        json: &str,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let json_value: JsonValue = serde_json::from_str(json).map_err(|_| AutomergeError::Fail)?;
         let segments = parse_path(path)?;
-        let mut tx = self.doc.transaction();
-
-        if segments.is_empty() {
+        let list_obj = if segments.is_empty() {
+            ROOT
+        } else {
+            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
+        };
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
             return Err(AutomergeError::Fail);
         }
-
-        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
-        let parent_obj = navigate_or_create_path(&mut tx, parent_path)?;
-
-        match &field_name[0] {
-            PathSegment::Key(key) => {
-                tx.put_object(&parent_obj, key.as_str(), automerge::ObjType::List)?;
-            }
-            PathSegment::Index(_) => {
-                return Err(AutomergeError::Fail); // Cannot create list at index
-            }
+        if index >= self.doc.length(&list_obj) {
+            return Err(AutomergeError::InvalidIndex(index));
         }
 
+        let mut tx = self.doc.transaction();
+        let segment = PathSegment::Index(index);
+        put_json_to_parent(&mut tx, &json_value, &list_obj, &segment, false, 1)?;
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -1510,14 +5635,26 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Appends a text value to the end of a list at the specified path.
+    /// Write a JSON value at `path`, replacing or merging with what is there.
     ///
-    /// The list must already exist at the given path.
+    /// With `merge` false the value at `path` is replaced outright by the
+    /// JSON, materialized as in [`Self::from_json`]. With `merge` true a JSON
+    /// object is deep-merged into an existing map: nested objects merge key
+    /// by key, other keys are overwritten, and keys the JSON does not
+    /// mention are kept. In both modes arrays replace the existing value
+    /// wholesale, and `null` stores a null rather than deleting. If the
+    /// JSON is not an object or the target is not a map, merging is the
+    /// same as replacing. Intermediate maps are created as needed, and the
+    /// whole write is one change.
+    ///
+    /// The empty path addresses the root, which only accepts a JSON object;
+    /// replacing the root removes every key the object does not contain.
     ///
     /// # Arguments
     ///
-    /// * `path` - Path to the list
-    /// * `value` - Text value to append
+    /// * `path` - Path to write at
+    /// * `json` - JSON value to write
+    /// * `merge` - Deep-merge objects into existing maps instead of replacing
     ///
     /// # Examples
     ///
@@ -1525,75 +5662,73 @@ impl RedisAutomergeClient {
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
     /// let mut client = RedisAutomergeClient::new();
-    /// client.create_list("users").unwrap();
-    /// client.append_text("users", "Alice").unwrap();
-    /// client.append_text("users", "Bob").unwrap();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.put_text("user.address.city", "Paris").unwrap();
     ///
-    /// assert_eq!(client.get_text("users[0]").unwrap(), Some("Alice".to_string()));
-    /// assert_eq!(client.list_len("users").unwrap(), Some(2));
+    /// client.put_json("user", r#"{"address":{"zip":"75001"}}"#, true).unwrap();
+    /// assert_eq!(client.get_text("user.name").unwrap(), Some("Alice".to_string()));
+    /// assert_eq!(client.get_text("user.address.city").unwrap(), Some("Paris".to_string()));
+    ///
+    /// client.put_json("user", r#"{"name":"Bob"}"#, false).unwrap();
+    /// assert!(!client.exists("user.address").unwrap());
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if the path doesn't exist or doesn't point to a list.
-    pub fn append_text(&mut self, path: &str, value: &str) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.append_text_with_change(path, value).map(|_| ())
+    /// Returns an error if the JSON is invalid or nested too deeply, if the
+    /// path is malformed or indexes past the end of a list, or if the path
+    /// is the root and the JSON is not an object.
+    pub fn put_json(&mut self, path: &str, json: &str, merge: bool) -> Result<(), AutomergeError> {
+        self.put_json_with_change(path, json, merge).map(|_| ())
     }
 
-    /// Append a text value to a list and return the raw change bytes.
-    pub fn append_text_with_change(
+    /// Write a JSON value at `path` and return the raw change bytes.
+    pub fn put_json_with_change(
         &mut self,
         path: &str,
-        value: &str,
+        json: &str,
+        merge: bool,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
-        let segments = parse_path(path)?;
-
-        // Navigate before creating transaction
-        let list_obj = if segments.is_empty() {
-            ROOT
-        } else {
-            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
-        };
-
-        let list_len = self.doc.length(&list_obj);
-        let mut tx = self.doc.transaction();
-        tx.insert(&list_obj, list_len, value)?;
-        let (hash, _patch) = tx.commit();
-
-        if let Some(h) = hash {
-            if let Some(change) = self.doc.get_change_by_hash(&h) {
-                return Ok(Some(change.raw_bytes().to_vec()));
-            }
-        }
-
-        Ok(None)
+        self.write_json(path, json, merge, false)
     }
 
-    /// Append an integer value to a list at the specified path.
-    pub fn append_int(&mut self, path: &str, value: i64) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.append_int_with_change(path, value).map(|_| ())
+    /// Like [`put_json_with_change`](Self::put_json_with_change), but
+    /// objects written by [`to_json_tagged`](Self::to_json_tagged) are
+    /// restored as the value they tag, as in
+    /// [`from_tagged_json`](Self::from_tagged_json).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client
+    ///     .put_tagged_json_with_change("stats", r#"{"views":{"$counter":5}}"#, false)
+    ///     .unwrap();
+    /// assert_eq!(client.get_counter("stats.views").unwrap(), Some(5));
+    /// ```
+    pub fn put_tagged_json_with_change(
+        &mut self,
+        path: &str,
+        json: &str,
+        merge: bool,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        self.write_json(path, json, merge, true)
     }
 
-    /// Append an integer value to a list and return the raw change bytes.
-    pub fn append_int_with_change(
+    fn write_json(
         &mut self,
         path: &str,
-        value: i64,
+        json: &str,
+        merge: bool,
+        tagged: bool,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        let json_value: JsonValue = serde_json::from_str(json).map_err(|_| AutomergeError::Fail)?;
         let segments = parse_path(path)?;
 
-        // Navigate before creating transaction
-        let list_obj = if segments.is_empty() {
-            ROOT
-        } else {
-            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
-        };
-
-        let list_len = self.doc.length(&list_obj);
         let mut tx = self.doc.transaction();
-        tx.insert(&list_obj, list_len, value)?;
+        write_json_at(&mut tx, &segments, &json_value, merge, tagged)?;
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -1605,30 +5740,77 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Append a double value to a list at the specified path.
-    pub fn append_double(&mut self, path: &str, value: f64) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.append_double_with_change(path, value).map(|_| ())
+    /// Apply a batch of edits, given as a JSON array, in one change.
+    ///
+    /// Each entry is `{"op":"put","path":...,"value":...}`, which writes the
+    /// JSON value as [`put_json`](Self::put_json) does without merging, or
+    /// `{"op":"del","path":...}`, which deletes as
+    /// [`delete_paths`](Self::delete_paths) does (a missing path is not an
+    /// error). Entries apply in order, so later ones see earlier ones.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.email", "old@example.com").unwrap();
+    ///
+    /// client.apply_patch(r#"[
+    ///     {"op": "put", "path": "user.name", "value": "Alice"},
+    ///     {"op": "del", "path": "user.email"}
+    /// ]"#).unwrap();
+    /// assert_eq!(client.get_text("user.name").unwrap(), Some("Alice".to_string()));
+    /// assert_eq!(client.get_text("user.email").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not an array of such entries, or if
+    /// any entry fails as its `put_json` or `delete_paths` counterpart
+    /// would. Nothing is written in that case.
+    pub fn apply_patch(&mut self, patch: &str) -> Result<(), AutomergeError> {
+        self.apply_patch_with_change(patch).map(|_| ())
     }
 
-    /// Append a double value to a list and return the raw change bytes.
-    pub fn append_double_with_change(
+    /// Apply a batch of edits and return the raw change bytes (`None` if
+    /// the batch changed nothing).
+    pub fn apply_patch_with_change(
         &mut self,
-        path: &str,
-        value: f64,
+        patch: &str,
     ) -> Result<Option<Vec<u8>>, AutomergeError> {
-        let segments = parse_path(path)?;
-
-        // Navigate before creating transaction
-        let list_obj = if segments.is_empty() {
-            ROOT
-        } else {
-            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
-        };
+        let entries: Vec<JsonValue> =
+            serde_json::from_str(patch).map_err(|_| AutomergeError::InvalidValueType {
+                expected: "a JSON array of patch entries".to_string(),
+                unexpected: "patch".to_string(),
+            })?;
 
-        let list_len = self.doc.length(&list_obj);
         let mut tx = self.doc.transaction();
-        tx.insert(&list_obj, list_len, value)?;
+        for (i, entry) in entries.iter().enumerate() {
+            let invalid = |expected: &str| AutomergeError::InvalidValueType {
+                expected: expected.to_string(),
+                unexpected: format!("patch entry {}", i),
+            };
+            let op = entry.get("op").and_then(JsonValue::as_str);
+            let path = entry
+                .get("path")
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| invalid("a string \"path\""))?;
+            let segments = parse_path(path)?;
+            match (op, entry.get("value")) {
+                (Some("put"), Some(value)) => {
+                    write_json_at(&mut tx, &segments, value, false, false)?
+                }
+                (Some("put"), None) => return Err(invalid("a \"value\" for put")),
+                (Some("del"), _) => match segments.split_last() {
+                    Some((last, parent_segments)) => {
+                        delete_at(&mut tx, parent_segments, last)?;
+                    }
+                    None => return Err(invalid("a non-root path for del")),
+                },
+                _ => return Err(invalid("\"op\" set to \"put\" or \"del\"")),
+            }
+        }
         let (hash, _patch) = tx.commit();
 
         if let Some(h) = hash {
@@ -1640,39 +5822,40 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
-    /// Append a boolean value to a list at the specified path.
-    pub fn append_bool(&mut self, path: &str, value: bool) -> Result<(), AutomergeError> {
-        // Audit #32: delegate to the `_with_change` variant.
-        self.append_bool_with_change(path, value).map(|_| ())
-    }
-
-    /// Append a boolean value to a list and return the raw change bytes.
-    pub fn append_bool_with_change(
-        &mut self,
-        path: &str,
-        value: bool,
-    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+    /// Check whether any value, of any type, is stored at `path`.
+    ///
+    /// The empty path names the document root, which always exists. A path
+    /// through a missing or scalar intermediate does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_int("user.age", 30).unwrap();
+    ///
+    /// assert!(client.exists("user").unwrap());
+    /// assert!(client.exists("user.age").unwrap());
+    /// assert!(!client.exists("user.name").unwrap());
+    /// assert!(!client.exists("user.age.years").unwrap());
+    /// ```
+    pub fn exists(&self, path: &str) -> Result<bool, AutomergeError> {
         let segments = parse_path(path)?;
+        if segments.is_empty() {
+            return Ok(true);
+        }
 
-        // Navigate before creating transaction
-        let list_obj = if segments.is_empty() {
+        let (parent_path, field_name) = segments.split_at(segments.len() - 1);
+        let parent_obj = if parent_path.is_empty() {
             ROOT
         } else {
-            navigate_path_read(&self.doc, &segments)?.ok_or(AutomergeError::Fail)?
-        };
-
-        let list_len = self.doc.length(&list_obj);
-        let mut tx = self.doc.transaction();
-        tx.insert(&list_obj, list_len, value)?;
-        let (hash, _patch) = tx.commit();
-
-        if let Some(h) = hash {
-            if let Some(change) = self.doc.get_change_by_hash(&h) {
-                return Ok(Some(change.raw_bytes().to_vec()));
+            match navigate_path_read(&self.doc, parent_path)? {
+                Some(obj) => obj,
+                None => return Ok(false),
             }
-        }
-
-        Ok(None)
+        };
+        Ok(get_value_from_parent(&self.doc, &parent_obj, &field_name[0])?.is_some())
     }
 
     /// Returns the length of a list at the specified path.
@@ -1695,23 +5878,29 @@ impl RedisAutomergeClient {
     ///
     /// assert_eq!(client.list_len("items").unwrap(), Some(2));
     /// assert_eq!(client.list_len("missing").unwrap(), None);
+    /// assert_eq!(client.list_len("").unwrap(), None);
     /// ```
     pub fn list_len(&self, path: &str) -> Result<Option<usize>, AutomergeError> {
         let segments = parse_path(path)?;
 
-        let list_obj = if segments.is_empty() {
-            ROOT
-        } else {
-            match navigate_path_read(&self.doc, &segments)? {
-                Some(obj) => obj,
-                None => return Ok(None),
-            }
+        // The root is always a map, so an empty path is never a list.
+        let list_obj = match navigate_path_read(&self.doc, &segments)? {
+            Some(obj) if !segments.is_empty() => obj,
+            _ => return Ok(None),
         };
 
+        // `length` also counts map keys and text characters; only report it
+        // for an actual list.
+        if self.doc.object_type(&list_obj)? != automerge::ObjType::List {
+            return Ok(None);
+        }
+
         Ok(Some(self.doc.length(&list_obj)))
     }
 
-    /// Returns the number of keys in a map at the specified path.
+    /// Returns the number of keys in a map at the specified path, not
+    /// counting the deadlines of fields written with
+    /// [`put_text_expiring`](Self::put_text_expiring).
     ///
     /// Returns `None` if the path doesn't exist or doesn't point to a map.
     ///
@@ -1743,42 +5932,438 @@ impl RedisAutomergeClient {
             }
         };
 
-        Ok(Some(self.doc.keys(&map_obj).count()))
+        let visible = self.doc.keys(&map_obj).filter(|key| {
+            !matches!(
+                self.doc.get(&map_obj, key.as_str()),
+                Ok(Some((value, _))) if is_expiry_companion(key, &value)
+            )
+        });
+        Ok(Some(visible.count()))
+    }
+
+    /// The size of the list or map at `path`: a list's length or a map's
+    /// key count, whichever the path holds. The root is a map.
+    ///
+    /// Returns `None` if nothing is stored at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_json("user", r#"{"name":"Alice","tags":["a","b","c"]}"#, false).unwrap();
+    ///
+    /// assert_eq!(client.container_len("user.tags").unwrap(), Some(3));
+    /// assert_eq!(client.container_len("user").unwrap(), Some(2));
+    /// assert!(client.container_len("user.name").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is malformed or holds a scalar or a
+    /// text object.
+    pub fn container_len(&self, path: &str) -> Result<Option<usize>, AutomergeError> {
+        let segments = parse_path(path)?;
+        let obj = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => {
+                    return match self.get_typed_value(path)? {
+                        Some(_) => Err(AutomergeError::Fail),
+                        None => Ok(None),
+                    }
+                }
+            }
+        };
+        match self.doc.object_type(&obj)? {
+            automerge::ObjType::List => Ok(Some(self.doc.length(&obj))),
+            automerge::ObjType::Map | automerge::ObjType::Table => {
+                Ok(Some(self.doc.keys(&obj).count()))
+            }
+            automerge::ObjType::Text => Err(AutomergeError::Fail),
+        }
+    }
+
+    /// Returns the kind of container at the specified path.
+    ///
+    /// The root is a map. Returns `None` if the path doesn't exist or points
+    /// to a scalar. Tables are reported as maps.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the container
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use automerge::ObjType;
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.create_list("items").unwrap();
+    /// client.put_text("name", "Alice").unwrap();
+    ///
+    /// assert_eq!(client.obj_type("items").unwrap(), Some(ObjType::List));
+    /// assert_eq!(client.obj_type("name").unwrap(), None);
+    /// ```
+    pub fn obj_type(&self, path: &str) -> Result<Option<automerge::ObjType>, AutomergeError> {
+        let segments = parse_path(path)?;
+
+        let obj = if segments.is_empty() {
+            ROOT
+        } else {
+            match navigate_path_read(&self.doc, &segments)? {
+                Some(obj) => obj,
+                None => return Ok(None),
+            }
+        };
+
+        match self.doc.object_type(&obj)? {
+            automerge::ObjType::Table => Ok(Some(automerge::ObjType::Map)),
+            obj_type => Ok(Some(obj_type)),
+        }
+    }
+
+    /// Lists every addressable path in the document, containers included.
+    ///
+    /// Paths use the same syntax `parse_path` accepts (`user.name`,
+    /// `users[0].email`) and are returned depth-first in document order:
+    /// a container's path comes right before the paths inside it. Text
+    /// objects are reported as leaves. Map keys that cannot be written as a
+    /// path segment (empty, or containing `.`, `[` or `]`) are skipped along
+    /// with everything beneath them.
+    ///
+    /// When `prefix` is given only paths starting with it are returned; a
+    /// leading `$.` on the prefix is ignored, as it is for paths.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.create_list("user.tags").unwrap();
+    /// client.append_text("user.tags", "admin").unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.paths(None),
+    ///     vec!["user", "user.name", "user.tags", "user.tags[0]"]
+    /// );
+    /// assert_eq!(client.paths(Some("user.t")), vec!["user.tags", "user.tags[0]"]);
+    /// ```
+    pub fn paths(&self, prefix: Option<&str>) -> Vec<String> {
+        let prefix = prefix.map(|p| p.strip_prefix("$.").unwrap_or(p));
+        self.walk_paths()
+            .into_iter()
+            .filter(|(path, _, _)| prefix.is_none_or(|p| path.starts_with(p)))
+            .map(|(path, _, _)| path)
+            .collect()
+    }
+
+    /// Every leaf under `prefix` with its value, in [`paths`](Self::paths)
+    /// order. Text objects are leaves and read as strings; empty maps and
+    /// lists have no leaves and are left out.
+    ///
+    /// Unlike [`paths`](Self::paths), `prefix` names a subtree rather than
+    /// the start of a string: `user` matches `user.name` and `user[0]` but
+    /// not `username`. `None` (or an empty prefix) covers the whole
+    /// document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, TypedValue};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.put_int("user.age", 30).unwrap();
+    /// client.put_text("username", "alice").unwrap();
+    ///
+    /// let leaves = client.leaves(Some("user")).unwrap();
+    /// assert_eq!(leaves.len(), 2);
+    /// assert!(leaves.contains(&("user.age".to_string(), TypedValue::Int(30))));
+    /// ```
+    pub fn leaves(
+        &self,
+        prefix: Option<&str>,
+    ) -> Result<Vec<(String, TypedValue)>, AutomergeError> {
+        let prefix = prefix
+            .map(|p| p.strip_prefix("$.").unwrap_or(p))
+            .filter(|p| !p.is_empty());
+        let under = |path: &str| match prefix {
+            None => true,
+            Some(p) => match path.strip_prefix(p) {
+                Some(rest) => rest.is_empty() || rest.starts_with(['.', '[']),
+                None => false,
+            },
+        };
+        let mut leaves = Vec::new();
+        for (path, container, _) in self.walk_paths() {
+            if container || !under(&path) {
+                continue;
+            }
+            match self.get_typed_value(&path)? {
+                // An expiry deadline, hidden as in `map_len` and `to_json`.
+                Some(TypedValue::Timestamp(_)) if path.ends_with(EXPIRES_SUFFIX) => {}
+                Some(value) => leaves.push((path, value)),
+                None => {}
+            }
+        }
+        Ok(leaves)
+    }
+
+    /// Every addressable path paired with the kind of value stored there,
+    /// in [`paths`](Self::paths) order: a machine-readable schema of the
+    /// document, containers and leaves alike.
+    ///
+    /// Containers are `map`, `list` or `text` (a text object). Scalars use
+    /// the type names of patch notifications: `str`, `int`, `uint`, `f64`,
+    /// `counter`, `timestamp_ms`, `bool`, `bytes` and `null`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.create_list("user.scores").unwrap();
+    /// client.append_int("user.scores", 10).unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.schema().unwrap(),
+    ///     vec![
+    ///         ("user".to_string(), "map"),
+    ///         ("user.name".to_string(), "str"),
+    ///         ("user.scores".to_string(), "list"),
+    ///         ("user.scores[0]".to_string(), "int"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn schema(&self) -> Result<Vec<(String, &'static str)>, AutomergeError> {
+        Ok(self
+            .walk_paths()
+            .into_iter()
+            .map(|(path, _, kind)| (path, kind))
+            .collect())
+    }
+
+    /// Every addressable path, depth-first in document order, paired with
+    /// whether it is a map or list that has paths beneath it and the type
+    /// name of its value.
+    fn walk_paths(&self) -> Vec<(String, bool, &'static str)> {
+        let mut paths = Vec::new();
+        // Explicit stack rather than recursion: documents built through
+        // AM.APPLY are not bounded by the from_json depth limit.
+        let mut stack = self.child_paths(&ROOT, "");
+        stack.reverse();
+        while let Some((path, kind, child)) = stack.pop() {
+            let container = child.is_some();
+            if let Some(obj) = child {
+                let mut children = self.child_paths(&obj, &path);
+                children.reverse();
+                stack.extend(children);
+            }
+            paths.push((path, container, kind));
+        }
+        paths
+    }
+
+    /// Paths of the direct children of `obj`, with the type name of each
+    /// child's value and its object id when it is a map or list that
+    /// [`paths`](Self::paths) should descend into.
+    fn child_paths(&self, obj: &ObjId, base: &str) -> Vec<(String, &'static str, Option<ObjId>)> {
+        let descend = |value: &Value, id: ObjId| match value {
+            Value::Object(automerge::ObjType::Text) | Value::Scalar(_) => None,
+            Value::Object(_) => Some(id),
+        };
+        let mut children = Vec::new();
+        match self.doc.object_type(obj) {
+            Ok(automerge::ObjType::Map) | Ok(automerge::ObjType::Table) => {
+                for key in self.doc.keys(obj) {
+                    if key.is_empty() || key.contains(['.', '[', ']']) {
+                        continue;
+                    }
+                    if let Ok(Some((value, id))) = self.doc.get(obj, key.as_str()) {
+                        let path = if base.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{}.{}", base, key)
+                        };
+                        children.push((path, value_type_name(&value), descend(&value, id)));
+                    }
+                }
+            }
+            Ok(automerge::ObjType::List) => {
+                for i in 0..self.doc.length(obj) {
+                    if let Ok(Some((value, id))) = self.doc.get(obj, i) {
+                        children.push((
+                            format!("{}[{}]", base, i),
+                            value_type_name(&value),
+                            descend(&value, id),
+                        ));
+                    }
+                }
+            }
+            Ok(automerge::ObjType::Text) | Err(_) => {}
+        }
+        children
+    }
+
+    /// Returns the document's current heads: the hashes of the changes no
+    /// other change depends on. Empty for a document with no changes.
+    pub fn get_heads(&self) -> Vec<ChangeHash> {
+        self.doc.get_heads()
     }
 
     /// Get changes from the document that are not in the provided have_deps list.
     ///
-    /// This exposes the Automerge `get_changes` API, which returns all changes
-    /// that are not in the provided list of change hashes. If an empty list is
-    /// provided, all changes in the document are returned.
+    /// This exposes the Automerge `get_changes` API, which returns all changes
+    /// that are not in the provided list of change hashes. If an empty list is
+    /// provided, all changes in the document are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `have_deps` - Slice of ChangeHash values representing changes already known
+    ///
+    /// # Returns
+    ///
+    /// A vector of Change references for changes not in have_deps
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("field", "value").unwrap();
+    ///
+    /// // Get all changes
+    /// let all_changes = client.get_changes(&[]);
+    /// assert_eq!(all_changes.len(), 1);
+    ///
+    /// // Get changes we don't have
+    /// let hash = all_changes[0].hash();
+    /// let new_changes = client.get_changes(&[hash]);
+    /// assert_eq!(new_changes.len(), 0);
+    /// ```
+    pub fn get_changes(&self, have_deps: &[ChangeHash]) -> Vec<Change> {
+        self.doc.get_changes(have_deps)
+    }
+
+    /// Hashes of the changes [`get_changes`](Self::get_changes) returns for
+    /// `have_deps`, in the same order. Only change metadata is read, so
+    /// this is much cheaper than building the changes.
+    pub fn change_hashes(&self, have_deps: &[ChangeHash]) -> Vec<ChangeHash> {
+        self.doc
+            .get_changes_meta(have_deps)
+            .into_iter()
+            .map(|c| c.hash)
+            .collect()
+    }
+
+    /// The changes hashed `hashes`, in that order. Hashes the document does
+    /// not have are skipped.
+    pub fn changes_by_hash(&self, hashes: &[ChangeHash]) -> Vec<Change> {
+        hashes
+            .iter()
+            .filter_map(|hash| self.doc.get_change_by_hash(hash))
+            .collect()
+    }
+
+    /// Hashes of the changes this document has and `other` does not, in this
+    /// document's change order.
+    ///
+    /// Called both ways round on two copies of a document, it shows exactly
+    /// where they diverged without syncing them. Held-back changes (see
+    /// [`pending_changes`](Self::pending_changes)) are not counted.
+    ///
+    /// # Examples
     ///
-    /// # Arguments
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
     ///
-    /// * `have_deps` - Slice of ChangeHash values representing changes already known
+    /// let mut a = RedisAutomergeClient::new();
+    /// a.put_int("x", 1).unwrap();
+    /// let mut b = RedisAutomergeClient::new();
+    /// b.apply_change_bytes(&a.get_changes(&[])[0].raw_bytes()).unwrap();
+    /// a.put_int("y", 2).unwrap();
     ///
-    /// # Returns
+    /// assert_eq!(a.changes_not_in(&b).len(), 1);
+    /// assert!(b.changes_not_in(&a).is_empty());
+    /// ```
+    pub fn changes_not_in(&self, other: &Self) -> Vec<ChangeHash> {
+        let theirs: std::collections::HashSet<ChangeHash> = other
+            .doc
+            .get_changes(&[])
+            .iter()
+            .map(|c| c.hash())
+            .collect();
+        self.doc
+            .get_changes(&[])
+            .iter()
+            .map(|c| c.hash())
+            .filter(|h| !theirs.contains(h))
+            .collect()
+    }
+
+    /// Hashes of changes this document has been asked to build on but has
+    /// never seen.
     ///
-    /// A vector of Change references for changes not in have_deps
+    /// A change applied before its dependencies is held back by Automerge
+    /// rather than rejected. It stays out of the document's state, heads
+    /// and [`get_changes`](Self::get_changes) until every dependency has
+    /// arrived. This lists the dependencies still missing, sorted; an empty
+    /// list means nothing is held back.
+    pub fn missing_deps(&self) -> Vec<ChangeHash> {
+        self.doc.get_missing_deps(&[])
+    }
+
+    /// The changes held back waiting for [`missing_deps`](Self::missing_deps).
+    ///
+    /// Automerge doesn't expose its queue directly, but appends the held
+    /// changes to the end of a full save, so they are recovered from there.
+    /// That costs two saves, so only call this when `missing_deps` is not
+    /// empty (it returns early otherwise).
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use redis_automerge::ext::RedisAutomergeClient;
     ///
-    /// let mut client = RedisAutomergeClient::new();
-    /// client.put_text("field", "value").unwrap();
-    ///
-    /// // Get all changes
-    /// let all_changes = client.get_changes(&[]);
-    /// assert_eq!(all_changes.len(), 1);
+    /// let mut source = RedisAutomergeClient::new();
+    /// source.put_text("a", "1").unwrap();
+    /// source.put_text("b", "2").unwrap();
+    /// let changes = source.get_changes(&[]);
     ///
-    /// // Get changes we don't have
-    /// let hash = all_changes[0].hash();
-    /// let new_changes = client.get_changes(&[hash]);
-    /// assert_eq!(new_changes.len(), 0);
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.apply_change_bytes(changes[1].raw_bytes()).unwrap();
+    /// assert_eq!(client.pending_changes().len(), 1);
+    /// assert_eq!(client.missing_deps(), vec![changes[0].hash()]);
     /// ```
-    pub fn get_changes(&self, have_deps: &[ChangeHash]) -> Vec<Change> {
-        self.doc.get_changes(have_deps)
+    pub fn pending_changes(&self) -> Vec<Change> {
+        if self.missing_deps().is_empty() {
+            return Vec::new();
+        }
+        let full = self.doc.save();
+        let settled = self.doc.save_with_options(automerge::SaveOptions {
+            retain_orphans: false,
+            ..Default::default()
+        });
+        let mut rest = full.get(settled.len()..).unwrap_or_default();
+        let mut pending = Vec::new();
+        while let Some((chunk, tail)) = split_chunk(rest) {
+            match Change::from_bytes(chunk.to_vec()) {
+                Ok(change) => pending.push(change),
+                Err(_) => break,
+            }
+            rest = tail;
+        }
+        pending
     }
 
     /// Get the diff between two document states.
@@ -1818,6 +6403,90 @@ impl RedisAutomergeClient {
         self.doc.diff(before_heads, after_heads)
     }
 
+    /// Compute the patches produced by a single change already present in
+    /// the document.
+    ///
+    /// The change is diffed from its own dependencies to its hash, so the
+    /// result describes exactly what that change did. For changes committed
+    /// locally the dependencies are the document heads at commit time,
+    /// which makes the patches equivalent to observing the transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `change_bytes` - Raw bytes of a change, as returned by the
+    ///   `_with_change` methods or accepted by `AM.APPLY`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid change or the change
+    /// has not been applied to this document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// let change = client.put_text_with_change("name", "Alice").unwrap().unwrap();
+    ///
+    /// let patches = client.patches_for_change(&change).unwrap();
+    /// assert_eq!(patches.len(), 1);
+    /// ```
+    pub fn patches_for_change(&self, change_bytes: &[u8]) -> Result<Vec<Patch>, AutomergeError> {
+        let change = Change::from_bytes(change_bytes.to_vec())?;
+        let hash = change.hash();
+        if self.doc.get_change_by_hash(&hash).is_none() {
+            return Err(AutomergeError::Fail);
+        }
+        Ok(self.doc.diff(change.deps(), &[hash]))
+    }
+
+    /// Byte length of the largest string, bytes or text value written by
+    /// `change_bytes`, a change already applied to this document. Values
+    /// are measured as they now stand, so a splice into a text counts the
+    /// whole text rather than the inserted fragment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid change or the change
+    /// has not been applied to this document.
+    pub fn largest_value_written(&self, change_bytes: &[u8]) -> Result<usize, AutomergeError> {
+        Ok(self.largest_patched_value(&self.patches_for_change(change_bytes)?))
+    }
+
+    /// Byte length of the largest string, bytes or text value in the
+    /// document.
+    pub fn largest_value(&self) -> usize {
+        self.largest_patched_value(&self.doc.diff(&[], &self.doc.get_heads()))
+    }
+
+    /// Largest string, bytes or text value touched by `patches`.
+    fn largest_patched_value(&self, patches: &[Patch]) -> usize {
+        let len = |value: &Value| match value {
+            Value::Scalar(s) => match s.as_ref() {
+                ScalarValue::Str(s) => s.len(),
+                ScalarValue::Bytes(b) => b.len(),
+                _ => 0,
+            },
+            Value::Object(_) => 0,
+        };
+        patches
+            .iter()
+            .map(|patch| match &patch.action {
+                PatchAction::PutMap { value: (v, _), .. }
+                | PatchAction::PutSeq { value: (v, _), .. } => len(v),
+                PatchAction::Insert { values, .. } => {
+                    values.iter().map(|(v, _, _)| len(v)).max().unwrap_or(0)
+                }
+                PatchAction::SpliceText { .. } => {
+                    self.doc.text(&patch.obj).map(|t| t.len()).unwrap_or(0)
+                }
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Resolve `path` to a Text object, converting an existing string
     /// scalar into a Text object in-place if needed.
     ///
@@ -1981,11 +6650,80 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
+    /// Splice text with `pos` and `del` counted in `unit`, returning the raw
+    /// change bytes.
+    ///
+    /// Both ends of the spliced range are converted to Unicode scalar
+    /// offsets first, so a client counting UTF-16 code units or bytes
+    /// edits exactly the characters it meant to. A range boundary that
+    /// falls inside a character is rejected rather than rounded, since
+    /// rounding would silently edit a neighbouring character. A negative
+    /// `del` deletes backwards from `pos`, as in
+    /// [`splice_text_with_change`](Self::splice_text_with_change).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::{RedisAutomergeClient, TextUnit};
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("msg", "a👋b").unwrap();
+    /// // In UTF-16 the emoji spans positions 1..3.
+    /// client.splice_text_in_with_change("msg", 1, 2, "!", TextUnit::Utf16).unwrap();
+    ///
+    /// assert_eq!(client.get_text("msg").unwrap(), Some("a!b".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidCharacter` if either end of the range splits a
+    /// character and `InvalidIndex` if it lies past the end of the text.
+    pub fn splice_text_in_with_change(
+        &mut self,
+        path: &str,
+        pos: usize,
+        del: isize,
+        text: &str,
+        unit: TextUnit,
+    ) -> Result<Option<Vec<u8>>, AutomergeError> {
+        if unit == TextUnit::CodePoints {
+            return self.splice_text_with_change(path, pos, del, text);
+        }
+        let current = self.get_text(path)?.unwrap_or_default();
+        let (start, end) = if del >= 0 {
+            (
+                pos,
+                pos.checked_add(del.unsigned_abs())
+                    .ok_or(AutomergeError::Fail)?,
+            )
+        } else {
+            let start = pos
+                .checked_sub(del.unsigned_abs())
+                .ok_or(AutomergeError::InvalidIndex(pos))?;
+            (start, pos)
+        };
+        let start = unit.to_char_offset(&current, start)?;
+        let end = unit.to_char_offset(&current, end)?;
+        let del = isize::try_from(end - start).map_err(|_| AutomergeError::Fail)?;
+        self.splice_text_with_change(path, start, del, text)
+    }
+
     /// Convert the entire Automerge document to JSON.
     ///
     /// Recursively traverses the document starting from ROOT and converts all
     /// values to JSON format. Supports both compact and pretty-printed output.
     ///
+    /// `F64` scalars are emitted in the shortest representation that parses
+    /// back to the identical bit pattern (serde_json formats floats via
+    /// `ryu`), so `0.1` is written as `0.1` rather than with float noise.
+    /// Integral doubles keep a fractional part (`2.0`, not `2`) so that a
+    /// `to_json` -> `from_json` round trip stores them as `F64` again
+    /// instead of demoting them to `Int`.
+    ///
+    /// The deadlines of fields written with
+    /// [`put_text_expiring`](Self::put_text_expiring) are left out, and so
+    /// are the fields themselves once their deadline has passed.
+    ///
     /// # Arguments
     ///
     /// * `pretty` - If true, output formatted JSON with indentation. If false, compact JSON.
@@ -2016,23 +6754,155 @@ impl RedisAutomergeClient {
     /// // }
     /// ```
     pub fn to_json(&self, pretty: bool) -> Result<String, AutomergeError> {
+        self.to_json_with_order(pretty, false, false, false)
+    }
+
+    /// Export the document to JSON with map keys in lexicographic order.
+    ///
+    /// Like [`to_json`](Self::to_json), but every object's keys are sorted
+    /// by their UTF-8 bytes before serialization. Two replicas holding the
+    /// same state therefore produce byte-identical output regardless of the
+    /// order in which their operations were applied, which makes the result
+    /// safe to hash for ETags or caches.
+    ///
+    /// `to_json` emits keys in the order the document iterates them. That
+    /// is currently lexicographic as well, but it is an implementation
+    /// detail of Automerge and serde_json rather than a guarantee.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("name", "Alice").unwrap();
+    /// client.put_int("age", 30).unwrap();
+    ///
+    /// assert_eq!(client.to_json_sorted(false).unwrap(), r#"{"age":30,"name":"Alice"}"#);
+    /// ```
+    pub fn to_json_sorted(&self, pretty: bool) -> Result<String, AutomergeError> {
+        self.to_json_with_order(pretty, true, false, false)
+    }
+
+    /// Export the document to JSON with integers JavaScript can't represent
+    /// exactly written as strings.
+    ///
+    /// `JSON.parse` reads every number as a double, so an integer beyond
+    /// ±(2^53 - 1) silently loses precision. Here such integers, counters
+    /// and unsigned integers become JSON strings (`"9007199254740993"`),
+    /// which a client can pass to `BigInt`. Smaller integers stay numbers.
+    /// `sorted` orders map keys as in [`to_json_sorted`](Self::to_json_sorted).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_int("small", 42).unwrap();
+    /// client.put_int("big", 9_007_199_254_740_993).unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.to_json_bigint_as_string(false, true).unwrap(),
+    ///     r#"{"big":"9007199254740993","small":42}"#
+    /// );
+    /// ```
+    pub fn to_json_bigint_as_string(
+        &self,
+        pretty: bool,
+        sorted: bool,
+    ) -> Result<String, AutomergeError> {
+        self.to_json_with_order(pretty, sorted, true, false)
+    }
+
+    /// Export the document to JSON with counters, timestamps and byte
+    /// strings written as tagged objects so their types survive a round
+    /// trip through [`from_tagged_json`](Self::from_tagged_json).
+    ///
+    /// A counter becomes `{"$counter": 5}`, a timestamp
+    /// `{"$timestamp": 1700000000000}` (milliseconds since the epoch) and a
+    /// byte string `{"$bytes": "<base64>"}`. A map whose only key is one of
+    /// these tag names, or `$map`, is wrapped as `{"$map": {...}}` so it
+    /// reads back as a map. Plain [`to_json`](Self::to_json)
+    /// writes them as a number, an RFC 3339 string and `null`. `sorted` and
+    /// `bigint_as_string` work as in
+    /// [`to_json_bigint_as_string`](Self::to_json_bigint_as_string).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_counter("views", 5).unwrap();
+    /// client.put_timestamp("created", 1_700_000_000_000).unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.to_json_tagged(false, true, false).unwrap(),
+    ///     r#"{"created":{"$timestamp":1700000000000},"views":{"$counter":5}}"#
+    /// );
+    /// ```
+    pub fn to_json_tagged(
+        &self,
+        pretty: bool,
+        sorted: bool,
+        bigint_as_string: bool,
+    ) -> Result<String, AutomergeError> {
+        self.to_json_with_order(pretty, sorted, bigint_as_string, true)
+    }
+
+    fn to_json_with_order(
+        &self,
+        pretty: bool,
+        sorted: bool,
+        bigint_as_string: bool,
+        tagged: bool,
+    ) -> Result<String, AutomergeError> {
         use serde_json::{Map, Value as JsonValue};
 
         // Helper function to recursively convert an Automerge object to JSON
-        fn obj_to_json(doc: &Automerge, obj_id: &ObjId) -> Result<JsonValue, AutomergeError> {
+        fn obj_to_json(
+            doc: &Automerge,
+            obj_id: &ObjId,
+            sorted: bool,
+            bigint_as_string: bool,
+            tagged: bool,
+            now_ms: i64,
+        ) -> Result<JsonValue, AutomergeError> {
             // Check the object type
             let obj_type = doc.object_type(obj_id)?;
 
             match obj_type {
                 automerge::ObjType::Map => {
                     let mut map = Map::new();
+                    let mut keys: Vec<String> = doc.keys(obj_id).collect();
+                    if sorted {
+                        keys.sort();
+                    }
                     // Iterate over all keys in the map
-                    for key in doc.keys(obj_id) {
+                    for key in keys {
                         if let Some((value, value_obj_id)) = doc.get(obj_id, &key)? {
-                            let json_value = value_to_json(doc, &value, &value_obj_id)?;
-                            map.insert(key.to_string(), json_value);
+                            if is_hidden_field(doc, obj_id, &key, &value, now_ms)? {
+                                continue;
+                            }
+                            let json_value = value_to_json(
+                                doc,
+                                &value,
+                                &value_obj_id,
+                                sorted,
+                                bigint_as_string,
+                                tagged,
+                                now_ms,
+                            )?;
+                            map.insert(key, json_value);
                         }
                     }
+                    // A map that would read back as a tag is wrapped in one.
+                    if tagged && map.len() == 1 && map.keys().all(|k| TAGS.contains(&k.as_str())) {
+                        let mut escaped = Map::new();
+                        escaped.insert(MAP_TAG.to_string(), JsonValue::Object(map));
+                        return Ok(JsonValue::Object(escaped));
+                    }
                     Ok(JsonValue::Object(map))
                 }
                 automerge::ObjType::List => {
@@ -2040,7 +6910,15 @@ impl RedisAutomergeClient {
                     let len = doc.length(obj_id);
                     for i in 0..len {
                         if let Some((value, value_obj_id)) = doc.get(obj_id, i)? {
-                            let json_value = value_to_json(doc, &value, &value_obj_id)?;
+                            let json_value = value_to_json(
+                                doc,
+                                &value,
+                                &value_obj_id,
+                                sorted,
+                                bigint_as_string,
+                                tagged,
+                                now_ms,
+                            )?;
                             arr.push(json_value);
                         }
                     }
@@ -2063,17 +6941,55 @@ impl RedisAutomergeClient {
             doc: &Automerge,
             value: &Value,
             obj_id: &ObjId,
+            sorted: bool,
+            bigint_as_string: bool,
+            tagged: bool,
+            now_ms: i64,
         ) -> Result<JsonValue, AutomergeError> {
+            // Largest integer a JavaScript double holds exactly (2^53 - 1).
+            const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+            let int_to_json = |i: i64| {
+                if bigint_as_string && i.unsigned_abs() > MAX_SAFE_INTEGER {
+                    JsonValue::String(i.to_string())
+                } else {
+                    JsonValue::Number(i.into())
+                }
+            };
             match value {
                 Value::Object(_) => {
                     // Recursively convert nested objects
-                    obj_to_json(doc, obj_id)
+                    obj_to_json(doc, obj_id, sorted, bigint_as_string, tagged, now_ms)
                 }
                 Value::Scalar(scalar) => {
                     let s = scalar.as_ref();
+                    let tag = |name: &str, value: JsonValue| {
+                        let mut map = Map::new();
+                        map.insert(name.to_string(), value);
+                        Ok(JsonValue::Object(map))
+                    };
                     match s {
+                        ScalarValue::Counter(c) if tagged => {
+                            tag(COUNTER_TAG, int_to_json(i64::from(c)))
+                        }
+                        ScalarValue::Timestamp(ts) if tagged => {
+                            tag(TIMESTAMP_TAG, int_to_json(*ts))
+                        }
+                        ScalarValue::Bytes(bytes) if tagged => {
+                            use base64::{engine::general_purpose, Engine as _};
+                            tag(
+                                BYTES_TAG,
+                                JsonValue::String(general_purpose::STANDARD.encode(bytes)),
+                            )
+                        }
                         ScalarValue::Str(s) => Ok(JsonValue::String(s.to_string())),
-                        ScalarValue::Int(i) => Ok(JsonValue::Number((*i).into())),
+                        ScalarValue::Int(i) => Ok(int_to_json(*i)),
+                        ScalarValue::Uint(u) => {
+                            if bigint_as_string && *u > MAX_SAFE_INTEGER {
+                                Ok(JsonValue::String(u.to_string()))
+                            } else {
+                                Ok(JsonValue::Number((*u).into()))
+                            }
+                        }
                         ScalarValue::F64(f) => {
                             if let Some(num) = serde_json::Number::from_f64(*f) {
                                 Ok(JsonValue::Number(num))
@@ -2081,7 +6997,7 @@ impl RedisAutomergeClient {
                                 Ok(JsonValue::Null)
                             }
                         }
-                        ScalarValue::Counter(c) => Ok(JsonValue::Number(i64::from(c).into())),
+                        ScalarValue::Counter(c) => Ok(int_to_json(i64::from(c))),
                         ScalarValue::Timestamp(ts) => {
                             // Convert Unix timestamp (milliseconds) to ISO 8601 string
                             let dt = DateTime::from_timestamp_millis(*ts)
@@ -2096,8 +7012,10 @@ impl RedisAutomergeClient {
             }
         }
 
-        // Start conversion from ROOT
-        let json_value = obj_to_json(&self.doc, &ROOT)?;
+        // Start conversion from ROOT, leaving out fields whose TTL has
+        // passed but which the reaper has not deleted yet.
+        let now_ms = Utc::now().timestamp_millis();
+        let json_value = obj_to_json(&self.doc, &ROOT, sorted, bigint_as_string, tagged, now_ms)?;
 
         // Serialize to string
         if pretty {
@@ -2107,6 +7025,45 @@ impl RedisAutomergeClient {
         }
     }
 
+    /// Export only the given paths as a compact JSON object, keeping each
+    /// value where it sits in the document.
+    ///
+    /// Map keys along a path become object keys and list indices become
+    /// array positions, with `null` filling the positions before them.
+    /// Paths that don't exist or whose TTL has passed are left out. A path
+    /// inside one that is already projected (`user` and `user.name`) adds
+    /// nothing new.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("user.name", "Alice").unwrap();
+    /// client.put_int("user.age", 30).unwrap();
+    /// client.put_text("user.bio", "A very long biography").unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.project(&["user.name", "user.age", "missing"]).unwrap(),
+    ///     r#"{"user":{"age":30,"name":"Alice"}}"#
+    /// );
+    /// ```
+    pub fn project(&self, paths: &[&str]) -> Result<String, AutomergeError> {
+        let now_ms = Utc::now().timestamp_millis();
+        let mut projected = JsonValue::Object(serde_json::Map::new());
+        for path in paths {
+            let segments = parse_path(path)?;
+            if self.is_expired(path, now_ms)? {
+                continue;
+            }
+            if let Some(value) = self.get_typed_value(path)? {
+                insert_projected(&mut projected, &segments, value.to_json());
+            }
+        }
+        serde_json::to_string(&projected).map_err(|_| AutomergeError::Fail)
+    }
+
     /// Create a new Automerge document from a JSON string.
     ///
     /// Parses the JSON string and recursively converts it to Automerge document structure:
@@ -2144,127 +7101,59 @@ impl RedisAutomergeClient {
     ///
     /// Returns an error if the JSON string cannot be parsed or converted to Automerge format.
     pub fn from_json(json: &str) -> Result<Self, AutomergeError> {
-        use serde_json::Value as JsonValue;
+        let mut client = Self::new();
+        client.fill_from_json(json, false)?;
+        Ok(client)
+    }
+
+    /// Like [`from_json`](Self::from_json), but objects written by
+    /// [`to_json_tagged`](Self::to_json_tagged) are restored as the scalar
+    /// they tag.
+    ///
+    /// An object with the single key `$counter`, `$timestamp` or `$bytes`
+    /// becomes a counter, a timestamp or a byte string. Counter and
+    /// timestamp values may be integers or decimal strings; byte strings are
+    /// standard padded base64. An object with the single key `$map` becomes
+    /// the map it wraps, taken as it is. A tag with any other value fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    ///
+    /// let client =
+    ///     RedisAutomergeClient::from_tagged_json(r#"{"views":{"$counter":5}}"#).unwrap();
+    /// assert_eq!(client.get_counter("views").unwrap(), Some(5));
+    /// ```
+    pub fn from_tagged_json(json: &str) -> Result<Self, AutomergeError> {
+        let mut client = Self::new();
+        client.fill_from_json(json, true)?;
+        Ok(client)
+    }
+
+    /// Like [`from_json`](Self::from_json), but the new document writes as
+    /// `actor`, including the change that stores the JSON (see
+    /// [`with_actor`](Self::with_actor)).
+    pub fn from_json_with_actor(json: &str, actor: &[u8]) -> Result<Self, AutomergeError> {
+        let mut client = Self::with_actor(actor);
+        client.fill_from_json(json, false)?;
+        Ok(client)
+    }
 
+    /// Store the top-level fields of `json`, which must be an object, into
+    /// this (empty) document in a single change. `tagged` restores the
+    /// objects [`from_tagged_json`](Self::from_tagged_json) recognizes.
+    fn fill_from_json(&mut self, json: &str, tagged: bool) -> Result<(), AutomergeError> {
         // Parse JSON string
         let json_value: JsonValue = serde_json::from_str(json).map_err(|_| AutomergeError::Fail)?;
 
-        // Create new document
-        let mut client = Self::new();
-        let mut tx = client.doc.transaction();
-
-        // Helper function to recursively populate an Automerge object from JSON
-        fn populate_from_json<T: Transactable>(
-            tx: &mut T,
-            parent: &ObjId,
-            key_or_index: KeyOrIndex,
-            value: &JsonValue,
-            depth: usize,
-        ) -> Result<(), AutomergeError> {
-            // Defense in depth against stack overflow from deeply nested JSON.
-            // serde_json caps parse-time recursion at 128 by default, but we
-            // enforce our own limit on the walker so future serde_json
-            // configuration changes cannot reach unbounded recursion here.
-            if depth > MAX_JSON_DEPTH {
-                return Err(AutomergeError::Fail);
-            }
-            match value {
-                JsonValue::Object(map) => {
-                    // Create a Map object
-                    let obj_id = match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            tx.put_object(parent, key.as_str(), automerge::ObjType::Map)?
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            tx.put_object(parent, idx, automerge::ObjType::Map)?
-                        }
-                    };
-                    // Recursively populate the map
-                    for (k, v) in map {
-                        populate_from_json(tx, &obj_id, KeyOrIndex::Key(k.clone()), v, depth + 1)?;
-                    }
-                }
-                JsonValue::Array(arr) => {
-                    // Create a List object
-                    let obj_id = match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            tx.put_object(parent, key.as_str(), automerge::ObjType::List)?
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            tx.put_object(parent, idx, automerge::ObjType::List)?
-                        }
-                    };
-                    // Append elements to the list
-                    for (i, v) in arr.iter().enumerate() {
-                        populate_from_json(tx, &obj_id, KeyOrIndex::Index(i), v, depth + 1)?;
-                    }
-                }
-                JsonValue::String(s) => {
-                    // Insert as text value
-                    match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            tx.put(parent, key.as_str(), s.as_str())?;
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            tx.insert(parent, idx, s.as_str())?;
-                        }
-                    }
-                }
-                JsonValue::Number(n) => {
-                    // Convert to int or double
-                    match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            if let Some(i) = n.as_i64() {
-                                tx.put(parent, key.as_str(), i)?;
-                            } else if let Some(f) = n.as_f64() {
-                                tx.put(parent, key.as_str(), f)?;
-                            }
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            if let Some(i) = n.as_i64() {
-                                tx.insert(parent, idx, i)?;
-                            } else if let Some(f) = n.as_f64() {
-                                tx.insert(parent, idx, f)?;
-                            }
-                        }
-                    }
-                }
-                JsonValue::Bool(b) => {
-                    // Insert as boolean
-                    match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            tx.put(parent, key.as_str(), *b)?;
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            tx.insert(parent, idx, *b)?;
-                        }
-                    }
-                }
-                JsonValue::Null => {
-                    // Insert as null
-                    match key_or_index {
-                        KeyOrIndex::Key(key) => {
-                            tx.put(parent, key.as_str(), ScalarValue::Null)?;
-                        }
-                        KeyOrIndex::Index(idx) => {
-                            tx.insert(parent, idx, ScalarValue::Null)?;
-                        }
-                    }
-                }
-            }
-            Ok(())
-        }
-
-        // Helper enum to handle both keys and indices
-        enum KeyOrIndex {
-            Key(String),
-            Index(usize),
-        }
+        let mut tx = self.doc.transaction();
 
         // Start populating from root
-        if let JsonValue::Object(map) = &json_value {
+        if let Some(map) = json_map_fields(&json_value, tagged)? {
             for (k, v) in map {
-                populate_from_json(&mut tx, &ROOT, KeyOrIndex::Key(k.clone()), v, 1)?;
+                let segment = PathSegment::Key(k.clone());
+                put_tagged_json_to_parent(&mut tx, v, &ROOT, &segment, false, tagged, 1)?;
             }
         } else {
             // If root is not an object, we can't convert it directly
@@ -2277,7 +7166,7 @@ impl RedisAutomergeClient {
         // change bytes locally. (Audit #9.)
         let (_hash, _patch) = tx.commit();
 
-        Ok(client)
+        Ok(())
     }
 
     /// Create a mark on a text object at the specified path.
@@ -2412,6 +7301,78 @@ impl RedisAutomergeClient {
         Ok(None)
     }
 
+    /// Get the text at `path` together with its marks, as the JSON object
+    /// `{"text": "...", "marks": [{"name", "value", "start", "end"}, ...]}`.
+    ///
+    /// Text and marks are read from the same document state, so the mark
+    /// ranges always index into the returned text. Mark values are plain
+    /// JSON (`true`, `"#ff0000"`, `3`) and marks are in the order
+    /// [`get_marks`](Self::get_marks) returns them. A plain string, such as
+    /// one written with [`put_text`](Self::put_text), has no marks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use redis_automerge::ext::RedisAutomergeClient;
+    /// use automerge::marks::ExpandMark;
+    ///
+    /// let mut client = RedisAutomergeClient::new();
+    /// client.put_text("doc", "Hello World").unwrap();
+    /// client.create_mark("doc", "bold", true.into(), 6, 11, ExpandMark::None).unwrap();
+    ///
+    /// assert_eq!(
+    ///     client.get_rich_text("doc").unwrap().unwrap(),
+    ///     r#"{"marks":[{"end":11,"name":"bold","start":6,"value":true}],"text":"Hello World"}"#
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if nothing is stored at `path`. A value that is
+    /// not text is reported as [`AutomergeError::InvalidValueType`]; an
+    /// invalid path keeps the error navigating it produced.
+    pub fn get_rich_text(&self, path: &str) -> Result<Option<String>, AutomergeError> {
+        let not_text = |unexpected: String| AutomergeError::InvalidValueType {
+            expected: "text".to_string(),
+            unexpected,
+        };
+        let segments = parse_path(path)?;
+        let text_obj = match navigate_path_read(&self.doc, &segments)? {
+            Some(obj) if !segments.is_empty() => obj,
+            _ => {
+                return match self.get_typed_value(path)? {
+                    Some(TypedValue::Text(text)) => Ok(Some(
+                        serde_json::json!({"text": text, "marks": []}).to_string(),
+                    )),
+                    Some(_) => Err(not_text("scalar".to_string())),
+                    None => Ok(None),
+                }
+            }
+        };
+        let obj_type = self.doc.object_type(&text_obj)?;
+        if obj_type != automerge::ObjType::Text {
+            return Err(not_text(obj_type.to_string()));
+        }
+
+        let text = self.doc.text(&text_obj)?;
+        let marks: Vec<JsonValue> = self
+            .doc
+            .marks(&text_obj)?
+            .into_iter()
+            .map(|m| {
+                serde_json::json!({
+                    "name": m.name(),
+                    "value": TypedValue::from(m.value()).to_json(),
+                    "start": m.start,
+                    "end": m.end,
+                })
+            })
+            .collect();
+        Ok(Some(
+            serde_json::json!({"text": text, "marks": marks}).to_string(),
+        ))
+    }
+
     /// Get all marks on a text object at the specified path.
     ///
     /// Returns a vector of marks containing their name, value, start, and end positions.
@@ -2476,8 +7437,13 @@ impl Default for RedisAutomergeClient {
 
 impl RedisAutomergeExt for RedisAutomergeClient {
     fn load(bytes: &[u8]) -> Result<Self, AutomergeError> {
-        let doc = Automerge::load(bytes)?;
-        Ok(Self { doc })
+        let doc = Automerge::load(&decompress_saved(bytes)?)?;
+        Ok(Self {
+            doc,
+            publish_patches: false,
+            max_bytes: 0,
+            measured_size: None,
+        })
     }
 
     fn save(&self) -> Vec<u8> {
@@ -71,6 +71,10 @@ val=$(redis-cli -h "$HOST" am.getcounter doc views)
 assert_equals "$val" "6"
 echo "   ✓ Counter decrement (negative increment) works"
 
+val=$(redis-cli -h "$HOST" am.getint doc views)
+assert_equals "$val" "6"
+echo "   ✓ Counters read as integers with AM.GETINT"
+
 # Test mixed types in same document
 echo "Test 6: Mixed types..."
 redis-cli -h "$HOST" am.puttext doc name "Alice" > /dev/null
@@ -158,5 +162,133 @@ after_mem=$(redis-cli -h "$HOST" memory usage memcheck)
 redis-cli -h "$HOST" del memcheck > /dev/null
 echo "   ✓ MEMORY USAGE reports non-zero, growing footprint for AM documents"
 
+echo "Test 10: DEFAULT fallback for typed getters..."
+redis-cli -h "$HOST" del defaults > /dev/null
+redis-cli -h "$HOST" am.new defaults > /dev/null
+redis-cli -h "$HOST" am.puttext defaults name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint defaults age 30 > /dev/null
+# Present: the stored value wins
+name=$(redis-cli -h "$HOST" --raw am.gettext defaults name DEFAULT "anonymous")
+assert_equals "$name" "Alice"
+# Absent with DEFAULT: the default is returned in the command's type
+name=$(redis-cli -h "$HOST" --raw am.gettext defaults nickname DEFAULT "anonymous")
+assert_equals "$name" "anonymous"
+retries=$(redis-cli -h "$HOST" am.getint defaults profile.retries DEFAULT 3)
+assert_equals "$retries" "3"
+ratio=$(redis-cli -h "$HOST" am.getdouble defaults ratio DEFAULT 0.5)
+assert_equals "$ratio" "0.5"
+verified=$(redis-cli -h "$HOST" am.getbool defaults verified DEFAULT true)
+assert_equals "$verified" "1"
+# Absent without DEFAULT: still null
+missing=$(redis-cli -h "$HOST" --raw am.gettext defaults nickname)
+assert_equals "$missing" ""
+# Type mismatch: the default does not apply
+mismatch=$(redis-cli -h "$HOST" --raw am.gettext defaults age DEFAULT "n/a")
+assert_equals "$mismatch" ""
+echo "   ✓ DEFAULT applies only to absent paths"
+
+echo "Test 11: AM.MGETKEYS reads one path across documents..."
+redis-cli -h "$HOST" del mget1 mget2 mget_missing > /dev/null
+redis-cli -h "$HOST" am.new mget1 > /dev/null
+redis-cli -h "$HOST" am.new mget2 > /dev/null
+redis-cli -h "$HOST" am.puttext mget1 user.name "Alice" > /dev/null
+redis-cli -h "$HOST" am.puttext mget2 user.name "Bob" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.mgetkeys user.name mget1 mget2 mget_missing | tr '\n' ',')
+assert_equals "$result" "Alice,Bob,,"
+echo "   ✓ AM.MGETKEYS returns aligned values with null for a missing key"
+
+echo "Test 12: AM.GETALLTYPED pairs each value with its actor..."
+redis-cli -h "$HOST" del getall1 > /dev/null
+redis-cli -h "$HOST" am.new getall1 ACTOR abcd > /dev/null
+redis-cli -h "$HOST" am.putint getall1 score 42 > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.getalltyped getall1 score | tr '\n' ',')
+assert_equals "$result" "abcd,42,"
+result=$(redis-cli -h "$HOST" am.getalltyped getall1 missing)
+assert_equals "$result" ""
+echo "   ✓ AM.GETALLTYPED returns [actor, value] pairs"
+
+echo "Test 13: AM.GETDOUBLE ASINT returns whole doubles as integers..."
+redis-cli -h "$HOST" del dbl1 > /dev/null
+redis-cli -h "$HOST" am.new dbl1 > /dev/null
+redis-cli -h "$HOST" am.putdouble dbl1 whole 5.0 > /dev/null
+redis-cli -h "$HOST" am.putdouble dbl1 frac 2.5 > /dev/null
+result=$(redis-cli --no-raw -3 -h "$HOST" am.getdouble dbl1 whole)
+assert_equals "$result" "(double) 5"
+result=$(redis-cli --no-raw -3 -h "$HOST" am.getdouble dbl1 whole ASINT)
+assert_equals "$result" "(integer) 5"
+result=$(redis-cli --no-raw -3 -h "$HOST" am.getdouble dbl1 frac ASINT)
+assert_equals "$result" "(double) 2.5"
+result=$(redis-cli --no-raw -3 -h "$HOST" am.getdouble dbl1 missing DEFAULT 7 ASINT)
+assert_equals "$result" "(integer) 7"
+echo "   ✓ ASINT only changes the reply type of whole doubles"
+
+echo "Test 14: AM.GETSET* returns the previous value..."
+redis-cli -h "$HOST" del getset1 > /dev/null
+redis-cli -h "$HOST" am.new getset1 > /dev/null
+redis-cli -h "$HOST" am.puttext getset1 status "pending" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.getsettext getset1 status "done")
+assert_equals "$result" "pending"
+result=$(redis-cli -h "$HOST" --raw am.gettext getset1 status)
+assert_equals "$result" "done"
+result=$(redis-cli -h "$HOST" --raw am.getsetint getset1 retries 3)
+assert_equals "$result" ""
+result=$(redis-cli -h "$HOST" --raw am.getsetint getset1 retries 0)
+assert_equals "$result" "3"
+echo "   ✓ GETSET swaps values and returns null for absent paths"
+
+echo "Test 15: AM.GETRAW returns encoded scalar bytes..."
+redis-cli -h "$HOST" del raw1 > /dev/null
+redis-cli -h "$HOST" am.new raw1 > /dev/null
+redis-cli -h "$HOST" am.puttext raw1 name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint raw1 age 30 > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.getraw raw1 name)
+assert_equals "$result" "Alice"
+result=$(redis-cli -h "$HOST" --raw am.getraw raw1 name ENCODING hex)
+assert_equals "$result" "416c696365"
+result=$(redis-cli -h "$HOST" --raw am.getraw raw1 name ENCODING base64)
+assert_equals "$result" "QWxpY2U="
+result=$(redis-cli -h "$HOST" --raw am.getraw raw1 age ENCODING hex)
+assert_equals "$result" "3330"
+result=$(redis-cli -h "$HOST" --raw am.getraw raw1 missing ENCODING hex)
+assert_equals "$result" ""
+echo "   ✓ AM.GETRAW encodes strings and numbers as hex and base64"
+
+echo "Test 16: AM.MINCRBY bumps several counters in one change..."
+redis-cli -h "$HOST" del mincr1 > /dev/null
+redis-cli -h "$HOST" am.new mincr1 > /dev/null
+redis-cli -h "$HOST" am.putcounter mincr1 score.home 1 > /dev/null
+before=$(redis-cli -h "$HOST" am.changes mincr1 COUNT)
+result=$(redis-cli -h "$HOST" am.mincrby mincr1 score.home 2 score.away 1 stats.shots 5 | tr '\n' ',')
+assert_equals "$result" "3,1,5,"
+after=$(redis-cli -h "$HOST" am.changes mincr1 COUNT)
+assert_equals "$after" "$((before + 1))"
+redis-cli -h "$HOST" am.putint mincr1 plain 7 > /dev/null
+result=$(redis-cli -h "$HOST" am.mincrby mincr1 score.home 1 plain 1 2>&1 || true)
+echo "$result" | grep -qi "counter" || { echo "   ✗ Expected an error for a non-counter path"; exit 1; }
+result=$(redis-cli -h "$HOST" am.getcounter mincr1 score.home)
+assert_equals "$result" "3"
+echo "   ✓ AM.MINCRBY applies all increments as one change, or none"
+
+echo "Test 17: AM.GETDOUBLECLAMP bounds a stored double..."
+redis-cli -h "$HOST" del clamp1 > /dev/null
+redis-cli -h "$HOST" am.new clamp1 > /dev/null
+redis-cli -h "$HOST" am.putdouble clamp1 sensors.ok 21.5 > /dev/null
+redis-cli -h "$HOST" am.putdouble clamp1 sensors.low -60.25 > /dev/null
+redis-cli -h "$HOST" am.putdouble clamp1 sensors.high 104.2 > /dev/null
+result=$(redis-cli -h "$HOST" am.getdoubleclamp clamp1 sensors.ok -40 100)
+assert_equals "$result" "21.5"
+result=$(redis-cli -h "$HOST" am.getdoubleclamp clamp1 sensors.low -40 100)
+assert_equals "$result" "-40"
+result=$(redis-cli -h "$HOST" am.getdoubleclamp clamp1 sensors.high -40 100)
+assert_equals "$result" "100"
+result=$(redis-cli -h "$HOST" am.getdoubleclamp clamp1 sensors.missing -40 100)
+assert_equals "$result" ""
+# The stored value is left alone
+result=$(redis-cli -h "$HOST" am.getdouble clamp1 sensors.high)
+assert_equals "$result" "104.2"
+result=$(redis-cli -h "$HOST" am.getdoubleclamp clamp1 sensors.ok 100 -40 2>&1 || true)
+echo "$result" | grep -qi "greater than max" || { echo "   ✗ Expected an error for min > max"; exit 1; }
+echo "   ✓ AM.GETDOUBLECLAMP clamps to min and max"
+
 echo ""
 echo "✅ All basic type tests passed!"
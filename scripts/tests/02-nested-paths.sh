@@ -99,6 +99,61 @@ assert_equals "$val1" "flat value"
 assert_equals "$val2" "nested value"
 echo "   ✓ Mixed flat and nested keys work"
 
+# Test exporting a subtree as its own document
+echo "Test 6: Export a subtree as a standalone document..."
+redis-cli -h "$HOST" del doc6 doc6_profile > /dev/null
+redis-cli -h "$HOST" am.new doc6 > /dev/null
+redis-cli -h "$HOST" am.puttext doc6 user.profile.name "Carol" > /dev/null
+redis-cli -h "$HOST" am.putint doc6 user.profile.age 41 > /dev/null
+redis-cli -h "$HOST" am.puttext doc6 user.email "carol@example.com" > /dev/null
+redis-cli -h "$HOST" --raw am.exportsubtree doc6 user.profile > /tmp/nested-subtree.bin
+truncate -s -1 /tmp/nested-subtree.bin
+redis-cli -h "$HOST" --raw -x am.load doc6_profile < /tmp/nested-subtree.bin > /dev/null
+val=$(redis-cli -h "$HOST" --raw am.gettext doc6_profile name)
+assert_equals "$val" "Carol"
+val=$(redis-cli -h "$HOST" am.getint doc6_profile age)
+assert_equals "$val" "41"
+val=$(redis-cli -h "$HOST" --raw am.gettext doc6_profile email)
+assert_equals "$val" ""
+result=$(redis-cli -h "$HOST" am.exportsubtree doc6 user.email 2>&1)
+echo "$result" | grep -qi "not a map" || { echo "   ✗ Expected an error for a non-map path"; exit 1; }
+echo "   ✓ Subtree export loads as an independent document"
+
+# Test reading every leaf as a map keyed by path
+echo "Test 7: AM.HGETALL maps paths to values (RESP3) or pairs them (RESP2)..."
+redis-cli -h "$HOST" del doc7 > /dev/null
+redis-cli -h "$HOST" am.new doc7 > /dev/null
+redis-cli -h "$HOST" am.puttext doc7 user.name "Dave" > /dev/null
+redis-cli -h "$HOST" am.putint doc7 user.age 52 > /dev/null
+redis-cli -h "$HOST" am.createlist doc7 user.tags > /dev/null
+redis-cli -h "$HOST" am.appendtext doc7 user.tags "ops" > /dev/null
+redis-cli -h "$HOST" am.puttext doc7 username "dave" > /dev/null
+result=$(redis-cli --no-raw -3 -h "$HOST" am.hgetall doc7 user)
+expected='1# "user.age" => (integer) 52
+2# "user.name" => "Dave"
+3# "user.tags[0]" => "ops"'
+assert_equals "$result" "$expected"
+result=$(redis-cli --no-raw -2 -h "$HOST" am.hgetall doc7 user)
+expected='1) "user.age"
+2) (integer) 52
+3) "user.name"
+4) "Dave"
+5) "user.tags[0]"
+6) "ops"'
+assert_equals "$result" "$expected"
+count=$(redis-cli -h "$HOST" am.hgetall doc7 | wc -l)
+assert_equals "$count" "8"
+echo "   ✓ AM.HGETALL returns every leaf keyed by its path"
+
+echo "Test 8: AM.SCHEMA types every path..."
+redis-cli -h "$HOST" del schema_doc > /dev/null
+redis-cli -h "$HOST" am.fromjson schema_doc '{"user":{"name":"Alice","tags":["admin"],"address":{"zip":12345}},"score":9.5,"active":true}' > /dev/null
+redis-cli -h "$HOST" am.putcounter schema_doc user.visits 1 > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.schema schema_doc | paste -sd ' ' -)
+assert_equals "$result" "active bool score f64 user map user.address map user.address.zip int user.name str user.tags list user.tags[0] str user.visits counter"
+echo "   ✓ Every container and leaf is listed with its type"
+
+rm -f /tmp/nested-subtree.bin
 rm -f /tmp/nested-saved.bin
 
 echo ""
@@ -66,6 +66,167 @@ assert_equals "$val1" "Alice"
 assert_equals "$val2" "Bob"
 echo "   ✓ List persistence works"
 
+# Test list slicing into a new key
+echo "Test 6: List slice into a new document..."
+redis-cli -h "$HOST" del doc8 doc8win > /dev/null
+redis-cli -h "$HOST" am.new doc8 > /dev/null
+redis-cli -h "$HOST" am.createlist doc8 log a b c d e > /dev/null
+redis-cli -h "$HOST" am.listslice doc8 log 1 -2 doc8win > /dev/null
+len=$(redis-cli -h "$HOST" am.listlen doc8win items)
+first=$(redis-cli -h "$HOST" --raw am.gettext doc8win 'items[0]')
+assert_equals "$len" "3"
+assert_equals "$first" "b"
+# Independent: editing the slice leaves the source alone
+redis-cli -h "$HOST" am.appendtext doc8win items "z" > /dev/null
+len=$(redis-cli -h "$HOST" am.listlen doc8 log)
+assert_equals "$len" "5"
+# Destination must not exist
+result=$(redis-cli -h "$HOST" am.listslice doc8 log 0 -1 doc8win 2>&1)
+echo "$result" | grep -qi "already exists"
+echo "   ✓ List slice works"
+
+# Test integer aggregation over a list
+echo "Test 7: List integer read and sum..."
+redis-cli -h "$HOST" del doc9 > /dev/null
+redis-cli -h "$HOST" am.fromjson doc9 '{"scores":[10,"n/a",20,2.5,30],"big":[9223372036854775807,1]}' > /dev/null
+ints=$(redis-cli -h "$HOST" --raw am.listints doc9 scores | tr '\n' ' ')
+assert_equals "$ints" "10 20 30 "
+sum=$(redis-cli -h "$HOST" am.listsumint doc9 scores)
+assert_equals "$sum" "60"
+result=$(redis-cli -h "$HOST" am.listsumint doc9 big 2>&1)
+echo "$result" | grep -qi "overflow"
+echo "   ✓ List integer aggregation works"
+
+# Test set-like appends
+echo "Test 8: Append with NX skips values already present..."
+redis-cli -h "$HOST" del doc10 > /dev/null
+redis-cli -h "$HOST" am.new doc10 > /dev/null
+added=$(redis-cli -h "$HOST" am.appendtext doc10 tags "admin" CREATE NX)
+assert_equals "$added" "1"
+added=$(redis-cli -h "$HOST" am.appendtext doc10 tags "admin" NX)
+assert_equals "$added" "0"
+# Types must match: the text "5" does not stop an integer 5
+added=$(redis-cli -h "$HOST" am.appendtext doc10 tags "5" NX)
+assert_equals "$added" "1"
+added=$(redis-cli -h "$HOST" am.appendint doc10 tags 5 NX)
+assert_equals "$added" "1"
+added=$(redis-cli -h "$HOST" am.appendint doc10 tags 5 NX)
+assert_equals "$added" "0"
+len=$(redis-cli -h "$HOST" am.listlen doc10 tags)
+assert_equals "$len" "3"
+# Without NX, duplicates are still appended
+result=$(redis-cli -h "$HOST" am.appendtext doc10 tags "admin")
+assert_equals "$result" "3"
+echo "   ✓ Append with NX works"
+
+# Test prepending
+echo "Test 9: AM.LISTPUSHFRONT inserts at the front..."
+redis-cli -h "$HOST" del doc11 > /dev/null
+redis-cli -h "$HOST" am.new doc11 > /dev/null
+redis-cli -h "$HOST" am.createlist doc11 feed > /dev/null
+redis-cli -h "$HOST" am.listpushfront doc11 feed '"first"' > /dev/null
+redis-cli -h "$HOST" am.listpushfront doc11 feed 2 > /dev/null
+redis-cli -h "$HOST" am.listpushfront doc11 feed '{"event":"third"}' > /dev/null
+json=$(redis-cli -h "$HOST" --raw am.tojson doc11)
+assert_equals "$json" '{"feed":[{"event":"third"},2,"first"]}'
+echo "   ✓ AM.LISTPUSHFRONT prepends in order"
+
+echo "Test 10: AM.LISTUPPER / AM.LISTLOWER only rewrite strings..."
+redis-cli -h "$HOST" del doc12 > /dev/null
+redis-cli -h "$HOST" am.new doc12 > /dev/null
+redis-cli -h "$HOST" am.createlist doc12 tags > /dev/null
+redis-cli -h "$HOST" am.appendtext doc12 tags "Red" > /dev/null
+redis-cli -h "$HOST" am.appendint doc12 tags 7 > /dev/null
+redis-cli -h "$HOST" am.appendtext doc12 tags "blue" > /dev/null
+redis-cli -h "$HOST" am.listupper doc12 tags > /dev/null
+json=$(redis-cli -h "$HOST" --raw am.tojson doc12)
+assert_equals "$json" '{"tags":["RED",7,"BLUE"]}'
+redis-cli -h "$HOST" am.listlower doc12 tags > /dev/null
+json=$(redis-cli -h "$HOST" --raw am.tojson doc12)
+assert_equals "$json" '{"tags":["red",7,"blue"]}'
+echo "   ✓ Case rewrites keep list length and skip non-strings"
+
+echo "Test 11: AM.LISTTRIM keeps only the last N elements..."
+redis-cli -h "$HOST" del doc13 > /dev/null
+redis-cli -h "$HOST" am.new doc13 > /dev/null
+redis-cli -h "$HOST" am.createlist doc13 log a b c d e > /dev/null
+redis-cli -h "$HOST" am.listtrim doc13 log -3 -1 > /dev/null
+len=$(redis-cli -h "$HOST" am.listlen doc13 log)
+assert_equals "$len" "3"
+json=$(redis-cli -h "$HOST" --raw am.tojson doc13)
+assert_equals "$json" '{"log":["c","d","e"]}'
+echo "   ✓ AM.LISTTRIM caps a list to a window"
+
+echo "Test 12: AM.LISTDISTINCTCOUNT counts distinct values..."
+redis-cli -h "$HOST" del doc14 > /dev/null
+redis-cli -h "$HOST" am.fromjson doc14 '{"tags":["red","blue","red",1,"1",1]}' > /dev/null
+count=$(redis-cli -h "$HOST" am.listdistinctcount doc14 tags)
+assert_equals "$count" "4"
+count=$(redis-cli -h "$HOST" am.listdistinctcount doc14 missing)
+assert_equals "$count" ""
+echo "   ✓ Duplicates across two types are counted once per type"
+
+echo "Test 13: AM.APPENDTEXTIFCHANGED skips a repeated tail..."
+redis-cli -h "$HOST" del doc15 > /dev/null
+redis-cli -h "$HOST" am.new doc15 > /dev/null
+redis-cli -h "$HOST" am.createlist doc15 status > /dev/null
+result=$(redis-cli -h "$HOST" am.appendtextifchanged doc15 status "online")
+assert_equals "$result" "1" "append to an empty list"
+result=$(redis-cli -h "$HOST" am.appendtextifchanged doc15 status "online")
+assert_equals "$result" "0" "skip a duplicate tail"
+result=$(redis-cli -h "$HOST" am.appendtextifchanged doc15 status "away")
+assert_equals "$result" "1" "append a different tail"
+result=$(redis-cli -h "$HOST" am.appendtextifchanged doc15 status "online")
+assert_equals "$result" "1" "only the tail is compared"
+json=$(redis-cli -h "$HOST" --raw am.tojson doc15)
+assert_equals "$json" '{"status":["online","away","online"]}'
+echo "   ✓ Only changes of status are recorded"
+
+echo "Test 14: AM.APPEND* reply with the new element's index..."
+redis-cli -h "$HOST" del doc16 > /dev/null
+redis-cli -h "$HOST" am.new doc16 > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.appendtext doc16 records "a" CREATE)" "0"
+assert_equals "$(redis-cli -h "$HOST" am.appendint doc16 records 2)" "1"
+assert_equals "$(redis-cli -h "$HOST" am.appendbool doc16 records true)" "2"
+result=$(redis-cli -h "$HOST" --raw am.gettext doc16 "records[0]")
+assert_equals "$result" "a"
+echo "   ✓ Returned indices are 0, 1, 2"
+
+echo "Test 15: AM.LISTSORT sorts scalars in place..."
+redis-cli -h "$HOST" del doc17 > /dev/null
+redis-cli -h "$HOST" am.new doc17 > /dev/null
+for n in 30 -2 7 100; do
+    redis-cli -h "$HOST" am.appendint doc17 scores "$n" CREATE > /dev/null
+done
+redis-cli -h "$HOST" am.listsort doc17 scores ASC > /dev/null
+assert_equals "$(redis-cli -h "$HOST" --raw am.listints doc17 scores | paste -sd ' ' -)" "-2 7 30 100"
+for tag in beta alpha gamma; do
+    redis-cli -h "$HOST" am.appendtext doc17 tags "$tag" CREATE > /dev/null
+done
+redis-cli -h "$HOST" am.listsort doc17 tags DESC > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson doc17 SORTED)
+assert_equals "$result" '{"scores":[-2,7,30,100],"tags":["gamma","beta","alpha"]}'
+redis-cli -h "$HOST" am.appendtext doc17 scores "oops" > /dev/null
+result=$(redis-cli -h "$HOST" am.listsort doc17 scores 2>&1)
+if echo "$result" | grep -qi "err\|fail"; then
+    echo "   ✓ Mixed-type list rejected"
+else
+    echo "   ✗ Mixed-type list not rejected (got: $result)"
+    exit 1
+fi
+echo "   ✓ Ints sort ascending and strings descending"
+
+echo "Test 16: AM.QUERY filters a list of records..."
+redis-cli -h "$HOST" del doc18 > /dev/null
+redis-cli -h "$HOST" am.fromjson doc18 '{"users":[{"name":"Alice","role":"admin","age":30},{"name":"Bob","role":"user","age":25},{"name":"Carol","role":"admin","age":41}]}' > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.query doc18 users role eq admin)
+assert_equals "$result" '[{"age":30,"name":"Alice","role":"admin"},{"age":41,"name":"Carol","role":"admin"}]'
+result=$(redis-cli -h "$HOST" --raw am.query doc18 users age gt 28)
+assert_equals "$result" '[{"age":30,"name":"Alice","role":"admin"},{"age":41,"name":"Carol","role":"admin"}]'
+result=$(redis-cli -h "$HOST" --raw am.query doc18 users age gt 50)
+assert_equals "$result" '[]'
+echo "   ✓ eq on a string field and gt on an int field"
+
 rm -f /tmp/list-saved.bin
 
 echo ""
@@ -90,6 +90,16 @@ val=$(redis-cli -h "$HOST" --raw am.gettext diff_audit18 doc)
 assert_equals "$val" "$expected"
 echo "   ✓ Past-end diff returns error and leaves document untouched"
 
+echo "Test 3d: PUTDIFF applies a multi-hunk git diff..."
+redis-cli -h "$HOST" del diff_test3d > /dev/null
+redis-cli -h "$HOST" am.new diff_test3d > /dev/null
+printf "1\n2\n3\n4\n5\n6\n7\n8" | redis-cli -h "$HOST" -x am.puttext diff_test3d doc > /dev/null
+printf -- "diff --git a/doc b/doc\n--- a/doc\n+++ b/doc\n@@ -1,2 +1,2 @@\n-1\n+one\n 2\n@@ -7,2 +7,2 @@\n 7\n-8\n\\\\ No newline at end of file\n+eight\n" | redis-cli -h "$HOST" -x am.putdiff diff_test3d doc > /dev/null
+val=$(redis-cli -h "$HOST" --raw am.gettext diff_test3d doc)
+expected=$(printf "one\n2\n3\n4\n5\n6\n7\neight\n")
+assert_equals "$val" "$expected"
+echo "   ✓ Multi-hunk diff with a no-newline marker applies at the right lines"
+
 # Test AM.SPLICETEXT command
 echo "Test 4: AM.SPLICETEXT with simple replacement..."
 redis-cli -h "$HOST" del splice_test1 > /dev/null
@@ -179,7 +189,117 @@ val=$(redis-cli -h "$HOST" --raw am.gettext splice_test7 doc)
 assert_equals "$val" "Hello Rust"
 echo "   ✓ AM.SPLICETEXT persistence works"
 
+# Test 11: Field-level expiry with AM.PUTTEXTEX
+echo "Test 11: AM.PUTTEXTEX field expiry..."
+redis-cli -h "$HOST" del expiry_test > /dev/null
+redis-cli -h "$HOST" am.new expiry_test > /dev/null
+redis-cli -h "$HOST" am.puttextex expiry_test presence.alice "online" 1 > /dev/null
+redis-cli -h "$HOST" am.puttext expiry_test presence.bob "online" > /dev/null
+val=$(redis-cli -h "$HOST" --raw am.gettext expiry_test presence.alice)
+assert_equals "$val" "online"
+# The deadline is not counted as a field of its own
+len=$(redis-cli -h "$HOST" am.maplen expiry_test presence)
+assert_equals "$len" "2"
+# Wait past the TTL and at least one reaper run
+sleep 3
+val=$(redis-cli -h "$HOST" --raw am.gettext expiry_test presence.alice)
+assert_equals "$val" ""
+# The reaper deleted the field and its companion, leaving only bob
+len=$(redis-cli -h "$HOST" am.maplen expiry_test presence)
+assert_equals "$len" "1"
+echo "   ✓ AM.PUTTEXTEX fields expire and are reaped"
+
+# An absolute PXAT deadline is stored as given
+deadline=$(( $(date +%s) * 1000 + 60000 ))
+redis-cli -h "$HOST" am.puttextex expiry_test presence.carol "online" PXAT "$deadline" > /dev/null
+val=$(redis-cli -h "$HOST" --raw am.gettext expiry_test presence.carol)
+assert_equals "$val" "online"
+at=$(redis-cli -h "$HOST" --raw am.gettimestamp expiry_test presence.carol__expires)
+assert_equals "$at" "$deadline"
+echo "   ✓ AM.PUTTEXTEX accepts an absolute PXAT deadline"
+
+# A deadline already in the past hides the field before the reaper runs
+redis-cli -h "$HOST" del expiry_test2 > /dev/null
+redis-cli -h "$HOST" am.new expiry_test2 > /dev/null
+redis-cli -h "$HOST" am.puttext expiry_test2 presence.bob "online" > /dev/null
+redis-cli -h "$HOST" am.puttextex expiry_test2 presence.alice "online" PXAT 1 > /dev/null
+len=$(redis-cli -h "$HOST" --raw am.strlen expiry_test2 presence.alice)
+assert_equals "$len" ""
+json=$(redis-cli -h "$HOST" --raw am.get expiry_test2 presence)
+assert_equals "$json" '{"bob":"online"}'
+json=$(redis-cli -h "$HOST" --raw am.tojson expiry_test2)
+assert_equals "$json" '{"presence":{"bob":"online"}}'
+json=$(redis-cli -h "$HOST" --raw am.project expiry_test2 presence.alice presence.bob)
+assert_equals "$json" '{"presence":{"bob":"online"}}'
+echo "   ✓ An expired field reads as null until it is reaped"
+
+# Copies and renamed keys keep their deadlines and are reaped too
+redis-cli -h "$HOST" del expiry_test3 expiry_test3_copy expiry_test3_renamed > /dev/null
+redis-cli -h "$HOST" am.new expiry_test3 > /dev/null
+redis-cli -h "$HOST" am.puttext expiry_test3 presence.bob "online" > /dev/null
+redis-cli -h "$HOST" am.puttextex expiry_test3 presence.alice "online" 1 > /dev/null
+redis-cli -h "$HOST" am.snapshot expiry_test3 expiry_test3_copy > /dev/null
+redis-cli -h "$HOST" rename expiry_test3 expiry_test3_renamed > /dev/null
+sleep 3
+for key in expiry_test3_copy expiry_test3_renamed; do
+    len=$(redis-cli -h "$HOST" am.maplen "$key" presence)
+    assert_equals "$len" "1"
+done
+echo "   ✓ The reaper follows AM.SNAPSHOT copies and RENAME"
+
 rm -f /tmp/splice-saved.bin
 
+echo "Test 12: UNIT converts positions around an emoji..."
+for spec in "codepoints 1 1" "utf16 1 2" "bytes 1 4"; do
+    set -- $spec
+    redis-cli -h "$HOST" del unit_test > /dev/null
+    redis-cli -h "$HOST" am.new unit_test > /dev/null
+    redis-cli -h "$HOST" am.puttext unit_test msg "a👋b" > /dev/null
+    redis-cli -h "$HOST" am.splicetext unit_test msg "$2" "$3" "!" UNIT "$1" > /dev/null
+    result=$(redis-cli -h "$HOST" --raw am.gettext unit_test msg)
+    assert_equals "$result" "a!b"
+done
+redis-cli -h "$HOST" am.puttext unit_test msg "a👋b" > /dev/null
+result=$(redis-cli -h "$HOST" am.strlen unit_test msg UNIT utf16)
+assert_equals "$result" "4"
+result=$(redis-cli -h "$HOST" am.splicetext unit_test msg 2 0 "x" UNIT utf16 2>&1)
+echo "$result" | grep -qi "invalid" || { echo "   ✗ Expected split-character error, got: $result"; exit 1; }
+echo "   ✓ SPLICETEXT and STRLEN honour codepoints, utf16 and bytes"
+
+echo "Test 13: AM.GETTEXT LEN truncates to whole characters..."
+redis-cli -h "$HOST" del len_test > /dev/null
+redis-cli -h "$HOST" am.new len_test > /dev/null
+redis-cli -h "$HOST" am.puttext len_test title "héllo wörld 👋🏽" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test title LEN 5)
+assert_equals "$result" "héllo"
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test title LEN 13)
+assert_equals "$result" "héllo wörld 👋"
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test title LEN 100)
+assert_equals "$result" "héllo wörld 👋🏽"
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test title LEN 0)
+assert_equals "$result" ""
+# Text objects truncate the same way, and LEN combines with DEFAULT
+redis-cli -h "$HOST" am.splicetext len_test title 0 0 "¡" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test title LEN 2)
+assert_equals "$result" "¡h"
+result=$(redis-cli -h "$HOST" --raw am.gettext len_test missing LEN 3 DEFAULT "añadir")
+assert_equals "$result" "aña"
+echo "   ✓ LEN returns at most N characters without splitting any"
+
+echo "Test 14: AM.PUTDIFF RETURN replies with the new text..."
+redis-cli -h "$HOST" del diff_return > /dev/null
+redis-cli -h "$HOST" am.new diff_return > /dev/null
+redis-cli -h "$HOST" am.puttext diff_return content "Hello World" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.putdiff diff_return content "--- a/content
++++ b/content
+@@ -1 +1 @@
+-Hello World
++Hello Automerge
+" RETURN)
+assert_equals "$result" "Hello Automerge"
+val=$(redis-cli -h "$HOST" --raw am.gettext diff_return content)
+assert_equals "$result" "$val"
+echo "   ✓ Returned text matches AM.GETTEXT"
+
 echo ""
 echo "✅ All text operation tests passed!"
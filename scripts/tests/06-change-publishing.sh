@@ -141,5 +141,49 @@ else
 fi
 rm -f /tmp/changes_all_list.txt
 
+echo "Test 16: An expiring document publishes a final expired message..."
+redis-cli -h "$HOST" del change_pub_ttl change_pub_del > /dev/null
+redis-cli -h "$HOST" am.new change_pub_ttl > /dev/null
+redis-cli -h "$HOST" am.new change_pub_del > /dev/null
+timeout 3 redis-cli -h "$HOST" PSUBSCRIBE "changes:change_pub_*" > /tmp/changes_ttl.txt 2>&1 &
+sub_pid=$!
+sleep 0.3
+redis-cli -h "$HOST" am.puttext change_pub_ttl status "live" > /dev/null
+redis-cli -h "$HOST" pexpire change_pub_ttl 200 > /dev/null
+# A document overwritten with another type is not reported when that expires
+redis-cli -h "$HOST" set change_pub_del "plain" px 300 > /dev/null
+sleep 1.5
+kill $sub_pid 2>/dev/null || true
+wait $sub_pid 2>/dev/null || true
+exists=$(redis-cli -h "$HOST" exists change_pub_ttl)
+assert_equals "$exists" "0"
+last=$(grep -A1 "^changes:change_pub_ttl$" /tmp/changes_ttl.txt | tail -1 || true)
+assert_equals "$last" "expired"
+if grep -q "^changes:change_pub_del$" /tmp/changes_ttl.txt; then
+    echo "   ✗ An overwritten document was reported as removed"
+    exit 1
+fi
+rm -f /tmp/changes_ttl.txt
+echo "   ✓ Subscribers see expired when the key's TTL runs out"
+
+echo "Test 17: Deleting a document publishes a deleted tombstone..."
+redis-cli -h "$HOST" del change_pub_gone1 change_pub_gone2 > /dev/null
+redis-cli -h "$HOST" am.new change_pub_gone1 > /dev/null
+redis-cli -h "$HOST" am.new change_pub_gone2 > /dev/null
+timeout 2 redis-cli -h "$HOST" PSUBSCRIBE "changes:change_pub_gone*" > /tmp/changes_gone.txt 2>&1 &
+sub_pid=$!
+sleep 0.3
+redis-cli -h "$HOST" del change_pub_gone1 > /dev/null
+redis-cli -h "$HOST" unlink change_pub_gone2 > /dev/null
+sleep 0.3
+kill $sub_pid 2>/dev/null || true
+wait $sub_pid 2>/dev/null || true
+for key in change_pub_gone1 change_pub_gone2; do
+    last=$(grep -A1 "^changes:$key$" /tmp/changes_gone.txt | tail -1 || true)
+    assert_equals "$last" "deleted"
+done
+rm -f /tmp/changes_gone.txt
+echo "   ✓ DEL and UNLINK publish deleted on the change channel"
+
 echo ""
 echo "✅ All change publishing tests passed!"
@@ -92,5 +92,217 @@ num_changes=$(redis-cli -h "$HOST" am.numchanges changes_test7)
 assert_equals "$num_changes" "3"
 echo "   ✓ AM.NUMCHANGES tracks nested path operations correctly"
 
+echo "Test 8: AM.TRUNCATEHISTORY discards overwritten values..."
+redis-cli -h "$HOST" del changes_test8 > /dev/null
+redis-cli -h "$HOST" am.new changes_test8 > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test8 email "secret@example.com" > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test8 email "public@example.com" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test8 age 30 > /dev/null
+redis-cli -h "$HOST" am.truncatehistory changes_test8 > /dev/null
+num_changes=$(redis-cli -h "$HOST" am.numchanges changes_test8)
+assert_equals "$num_changes" "1"
+email=$(redis-cli -h "$HOST" --raw am.gettext changes_test8 email)
+assert_equals "$email" "public@example.com"
+if redis-cli -h "$HOST" --raw am.changes changes_test8 | grep -aq "secret@example.com"; then
+    echo "   ✗ Overwritten value still present in change history"
+    exit 1
+fi
+echo "   ✓ AM.TRUNCATEHISTORY keeps current state and drops old values"
+
+echo "Test 9: AM.WAITCHANGE is woken by a write on another connection..."
+redis-cli -h "$HOST" del changes_test9 > /dev/null
+redis-cli -h "$HOST" am.new changes_test9 > /dev/null
+# An empty document has no heads, so waiting with none known blocks.
+redis-cli -h "$HOST" am.waitchange changes_test9 5000 > /tmp/waitchange.out &
+waiter=$!
+sleep 0.3
+redis-cli -h "$HOST" am.puttext changes_test9 name "Alice" > /dev/null
+wait $waiter
+# The reply is the new head: one raw 32-byte hash (plus newline).
+bytes=$(wc -c < /tmp/waitchange.out | tr -d ' ')
+assert_equals "$bytes" "33"
+# Known heads that are already stale return at once, even with no timeout.
+bytes=$(redis-cli -h "$HOST" am.waitchange changes_test9 0 | wc -c | tr -d ' ')
+assert_equals "$bytes" "33"
+# Nothing written before the timeout: null.
+redis-cli -h "$HOST" del changes_test9b > /dev/null
+redis-cli -h "$HOST" am.new changes_test9b > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.waitchange changes_test9b 100)
+assert_equals "$result" ""
+rm -f /tmp/waitchange.out
+echo "   ✓ AM.WAITCHANGE returns new heads or times out"
+
+echo "Test 10: AM.MEMBREAKDOWN reports history that truncation removes..."
+redis-cli -h "$HOST" del changes_test10 > /dev/null
+redis-cli -h "$HOST" am.new changes_test10 > /dev/null
+for i in $(seq 1 50); do
+    redis-cli -h "$HOST" am.putint changes_test10 n "$i" > /dev/null
+done
+changes=$(redis-cli -h "$HOST" --raw am.membreakdown changes_test10 | grep -A1 "^changes$" | tail -1)
+assert_equals "$changes" "50"
+before=$(redis-cli -h "$HOST" --raw am.membreakdown changes_test10 | grep -A1 "^history_bytes$" | tail -1)
+redis-cli -h "$HOST" am.truncatehistory changes_test10 > /dev/null
+after=$(redis-cli -h "$HOST" --raw am.membreakdown changes_test10 | grep -A1 "^history_bytes$" | tail -1)
+if [ "$after" -ge "$before" ]; then
+    echo "   ✗ Expected history_bytes to shrink ($before -> $after)"
+    exit 1
+fi
+echo "   ✓ AM.MEMBREAKDOWN tracks history size"
+
+echo "Test 11: AM.NEW ACTOR makes change hashes reproducible..."
+redis-cli -h "$HOST" del changes_test11a changes_test11b > /dev/null
+for key in changes_test11a changes_test11b; do
+    redis-cli -h "$HOST" am.new "$key" ACTOR 0123456789abcdef0123456789ABCDEF > /dev/null
+    redis-cli -h "$HOST" am.puttext "$key" name "Alice" > /dev/null
+    redis-cli -h "$HOST" am.putint "$key" age 30 > /dev/null
+done
+hash_a=$(redis-cli -h "$HOST" --raw am.changes changes_test11a | md5sum)
+hash_b=$(redis-cli -h "$HOST" --raw am.changes changes_test11b | md5sum)
+assert_equals "$hash_a" "$hash_b"
+result=$(redis-cli -h "$HOST" am.new changes_test11c ACTOR xyz 2>&1)
+echo "$result" | grep -qi "hex" || { echo "   ✗ Expected an error for a bad actor id"; exit 1; }
+echo "   ✓ Documents with the same actor and edits have identical changes"
+
+echo "Test 12: AM.PENDING reports nothing pending for a settled document..."
+result=$(redis-cli -h "$HOST" am.pending changes_test11a | tr '\n' ',')
+assert_equals "$result" "0,"
+echo "   ✓ AM.PENDING returns a zero count and no missing hashes"
+
+echo "Test 13: AM.SNAPSHOT copies state without history..."
+redis-cli -h "$HOST" del changes_test13 changes_test13_copy > /dev/null
+redis-cli -h "$HOST" am.new changes_test13 > /dev/null
+for i in $(seq 1 20); do
+    redis-cli -h "$HOST" am.putint changes_test13 revision "$i" > /dev/null
+done
+redis-cli -h "$HOST" am.puttext changes_test13 title "Board" > /dev/null
+redis-cli -h "$HOST" am.snapshot changes_test13 changes_test13_copy > /dev/null
+num_changes=$(redis-cli -h "$HOST" am.numchanges changes_test13_copy)
+assert_equals "$num_changes" "1"
+src_json=$(redis-cli -h "$HOST" --raw am.tojson changes_test13)
+dst_json=$(redis-cli -h "$HOST" --raw am.tojson changes_test13_copy)
+assert_equals "$dst_json" "$src_json"
+result=$(redis-cli -h "$HOST" am.snapshot changes_test13 changes_test13_copy 2>&1)
+echo "$result" | grep -qi "already exists" || { echo "   ✗ Expected an error for an existing destination"; exit 1; }
+echo "   ✓ AM.SNAPSHOT creates a single-change copy"
+
+echo "Test 14: AM.HEAVYPATHS ranks the largest entry first..."
+redis-cli -h "$HOST" del changes_test14 > /dev/null
+redis-cli -h "$HOST" am.new changes_test14 > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test14 title "Notes" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test14 user.age 30 > /dev/null
+redis-cli -h "$HOST" am.createlist changes_test14 user.log > /dev/null
+for i in $(seq 1 100); do
+    redis-cli -h "$HOST" am.appendtext changes_test14 user.log "entry number $i" > /dev/null
+done
+top=$(redis-cli -h "$HOST" --raw am.heavypaths changes_test14 1 | head -1)
+assert_equals "$top" "user"
+# Reply holds a path and a size for each entry
+lines=$(redis-cli -h "$HOST" --raw am.heavypaths changes_test14 | wc -l)
+assert_equals "$lines" "4"
+second=$(redis-cli -h "$HOST" --raw am.heavypaths changes_test14 2 RECURSIVE | sed -n 3p)
+assert_equals "$second" "user.log"
+echo "   ✓ AM.HEAVYPATHS finds the large list"
+
+echo "Test 15: AM.SAVE CANONICAL gives equal bytes for equal states..."
+redis-cli -h "$HOST" del changes_test15a changes_test15b > /dev/null
+redis-cli -h "$HOST" am.new changes_test15a > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test15a name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test15a age 30 > /dev/null
+redis-cli -h "$HOST" am.new changes_test15b > /dev/null
+redis-cli -h "$HOST" am.putint changes_test15b age 29 > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test15b name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test15b age 30 > /dev/null
+plain_a=$(redis-cli -h "$HOST" --raw am.save changes_test15a | md5sum)
+plain_b=$(redis-cli -h "$HOST" --raw am.save changes_test15b | md5sum)
+if [ "$plain_a" = "$plain_b" ]; then
+    echo "   ✗ Expected plain saves of different histories to differ"
+    exit 1
+fi
+canon_a=$(redis-cli -h "$HOST" --raw am.save changes_test15a CANONICAL | md5sum)
+canon_b=$(redis-cli -h "$HOST" --raw am.save changes_test15b CANONICAL | md5sum)
+assert_equals "$canon_a" "$canon_b"
+canon_a=$(redis-cli -h "$HOST" --raw am.save changes_test15a CANONICAL COMPRESS | md5sum)
+canon_b=$(redis-cli -h "$HOST" --raw am.save changes_test15b COMPRESS CANONICAL | md5sum)
+assert_equals "$canon_a" "$canon_b"
+echo "   ✓ AM.SAVE CANONICAL ignores history"
+
+echo "Test 16: AM.FORKAT starts a document from one subtree..."
+redis-cli -h "$HOST" del changes_test16 changes_test16_fork > /dev/null
+redis-cli -h "$HOST" am.new changes_test16 > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test16 public.title "Roadmap" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test16 public.stats.views 7 > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test16 private.notes "internal" > /dev/null
+redis-cli -h "$HOST" am.puttext changes_test16 public.title "Roadmap 2026" > /dev/null
+redis-cli -h "$HOST" am.forkat changes_test16 public changes_test16_fork > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson changes_test16_fork SORTED)
+assert_equals "$result" '{"stats":{"views":7},"title":"Roadmap 2026"}'
+num_changes=$(redis-cli -h "$HOST" am.numchanges changes_test16_fork)
+assert_equals "$num_changes" "1"
+redis-cli -h "$HOST" am.puttext changes_test16_fork title "Shared" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.gettext changes_test16 public.title)
+assert_equals "$result" "Roadmap 2026"
+result=$(redis-cli -h "$HOST" am.forkat changes_test16 public changes_test16_fork 2>&1)
+echo "$result" | grep -qi "already exists" || { echo "   ✗ Expected an error for an existing destination"; exit 1; }
+result=$(redis-cli -h "$HOST" am.forkat changes_test16 public.title changes_test16_other 2>&1)
+echo "$result" | grep -qi "not a map" || { echo "   ✗ Expected an error for a non-map path"; exit 1; }
+echo "   ✓ AM.FORKAT copies only the subtree"
+
+echo "Test 17: AM.INSTANTIATE copies a template with overrides merged in..."
+redis-cli -h "$HOST" del changes_test17_tpl changes_test17_acme > /dev/null
+redis-cli -h "$HOST" am.new changes_test17_tpl FROM '{"plan":"free","limits":{"seats":5,"storage":10}}' > /dev/null
+redis-cli -h "$HOST" am.instantiate changes_test17_acme FROM changes_test17_tpl WITH '{"name":"Acme","limits":{"seats":50}}' > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson changes_test17_acme SORTED)
+assert_equals "$result" '{"limits":{"seats":50,"storage":10},"name":"Acme","plan":"free"}'
+result=$(redis-cli -h "$HOST" am.getint changes_test17_tpl limits.seats)
+assert_equals "$result" "5"
+result=$(redis-cli -h "$HOST" am.instantiate changes_test17_acme FROM changes_test17_tpl WITH '{}' 2>&1)
+echo "$result" | grep -qi "already exists" || { echo "   ✗ Expected an error for an existing destination"; exit 1; }
+result=$(redis-cli -h "$HOST" am.instantiate changes_test17_other FROM changes_test17_tpl WITH '[1]' 2>&1)
+echo "$result" | grep -qi "JSON object" || { echo "   ✗ Expected an error for non-object overrides"; exit 1; }
+result=$(redis-cli -h "$HOST" am.instantiate changes_test17_other FROM changes_test17_tpl WITH '{"a":' 2>&1)
+echo "$result" | grep -q "invalid JSON" || { echo "   ✗ Expected an error for malformed overrides"; exit 1; }
+exists=$(redis-cli -h "$HOST" exists changes_test17_other)
+assert_equals "$exists" "0"
+echo "   ✓ AM.INSTANTIATE keeps template defaults and applies overrides"
+
+echo "Test 18: AM.CHANGES COUNT returns the number of changes..."
+redis-cli -h "$HOST" del changes_test18 > /dev/null
+redis-cli -h "$HOST" am.new changes_test18 > /dev/null
+result=$(redis-cli -h "$HOST" am.changes changes_test18 COUNT)
+assert_equals "$result" "0"
+redis-cli -h "$HOST" am.puttext changes_test18 name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint changes_test18 age 30 > /dev/null
+redis-cli -h "$HOST" am.putint changes_test18 age 31 > /dev/null
+result=$(redis-cli -h "$HOST" am.changes changes_test18 count)
+assert_equals "$result" "3"
+num_changes=$(redis-cli -h "$HOST" am.numchanges changes_test18)
+assert_equals "$num_changes" "3"
+result=$(redis-cli -h "$HOST" am.changes changes_test18 COUNT LIMIT 2 2>&1)
+echo "$result" | grep -qi "cannot be combined with COUNT" || { echo "   ✗ Expected an error for COUNT with LIMIT"; exit 1; }
+echo "   ✓ AM.CHANGES COUNT matches the changes made"
+
+echo "Test 19: Reloading a document does not fail its waiters on a later EXPIRE..."
+redis-cli -h "$HOST" del changes_test19 > /dev/null
+redis-cli -h "$HOST" am.new changes_test19 > /dev/null
+redis-cli -h "$HOST" --raw am.save changes_test19 > /tmp/changes_test19.am
+truncate -s -1 /tmp/changes_test19.am
+# AM.LOAD over the existing document replaces it under the same key.
+redis-cli -h "$HOST" --raw -x am.load changes_test19 < /tmp/changes_test19.am > /dev/null
+redis-cli -h "$HOST" am.waitchange changes_test19 5000 > /tmp/waitchange19.out 2>&1 &
+waiter=$!
+sleep 0.3
+redis-cli -h "$HOST" expire changes_test19 60 > /dev/null
+sleep 0.3
+if ! kill -0 $waiter 2>/dev/null; then
+    echo "   ✗ AM.WAITCHANGE returned after EXPIRE: $(cat /tmp/waitchange19.out)"
+    exit 1
+fi
+redis-cli -h "$HOST" am.puttext changes_test19 name "Alice" > /dev/null
+wait $waiter
+bytes=$(wc -c < /tmp/waitchange19.out | tr -d ' ')
+assert_equals "$bytes" "33"
+rm -f /tmp/changes_test19.am /tmp/waitchange19.out
+echo "   ✓ A reloaded document's waiters stay blocked until it changes"
+
 echo ""
 echo "✅ All change management tests passed!"
@@ -307,5 +307,95 @@ exported=$(redis-cli -h "$HOST" --raw am.tojson fromjson_test8)
 assert_equals "$exported" "{}"
 echo "   ✓ AM.FROMJSON empty object works"
 
+echo "Test 18: AM.NEW FROM with a nested template..."
+redis-cli -h "$HOST" del new_from_test1 > /dev/null
+json='{"title":"Untitled","meta":{"version":1},"sections":[{"heading":"Intro","tags":["a","b"]}]}'
+redis-cli -h "$HOST" am.new new_from_test1 FROM "$json" > /dev/null
+title=$(redis-cli -h "$HOST" --raw am.gettext new_from_test1 title)
+version=$(redis-cli -h "$HOST" am.getint new_from_test1 meta.version)
+heading=$(redis-cli -h "$HOST" --raw am.gettext new_from_test1 'sections[0].heading')
+tag1=$(redis-cli -h "$HOST" --raw am.gettext new_from_test1 'sections[0].tags[1]')
+assert_equals "$title" "Untitled"
+assert_equals "$version" "1"
+assert_equals "$heading" "Intro"
+assert_equals "$tag1" "b"
+echo "   ✓ AM.NEW FROM creates the document from the template"
+
+echo "Test 19: AM.NEW FROM refuses an existing key..."
+result=$(redis-cli -h "$HOST" am.new new_from_test1 FROM '{"title":"Other"}' 2>&1 || true)
+if echo "$result" | grep -q "key already exists"; then
+    echo "   ✓ AM.NEW FROM rejected existing key"
+else
+    echo "   ✗ AM.NEW FROM did not reject existing key (got: $result)"
+    exit 1
+fi
+title=$(redis-cli -h "$HOST" --raw am.gettext new_from_test1 title)
+assert_equals "$title" "Untitled"
+echo "   ✓ Existing document left untouched"
+
+echo "Test 20: AM.TOJSON BIGINT_AS_STRING quotes unsafe integers..."
+redis-cli -h "$HOST" del json_bigint > /dev/null
+redis-cli -h "$HOST" am.new json_bigint > /dev/null
+redis-cli -h "$HOST" am.putint json_bigint big 9007199254740993 > /dev/null
+redis-cli -h "$HOST" am.putint json_bigint small 42 > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson json_bigint SORTED)
+assert_equals "$result" '{"big":9007199254740993,"small":42}'
+result=$(redis-cli -h "$HOST" --raw am.tojson json_bigint SORTED BIGINT_AS_STRING)
+assert_equals "$result" '{"big":"9007199254740993","small":42}'
+echo "   ✓ Only integers beyond 2^53 - 1 become strings"
+
+echo "Test 21: AM.APPLYPATCH applies a batch atomically..."
+redis-cli -h "$HOST" del json_patch > /dev/null
+redis-cli -h "$HOST" am.new json_patch > /dev/null
+redis-cli -h "$HOST" am.puttext json_patch user.email "old@example.com" > /dev/null
+before=$(redis-cli -h "$HOST" am.numchanges json_patch)
+redis-cli -h "$HOST" am.applypatch json_patch '[{"op":"put","path":"user.name","value":"Alice"},{"op":"put","path":"user.tags","value":["admin"]},{"op":"del","path":"user.email"}]' > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson json_patch SORTED)
+assert_equals "$result" '{"user":{"name":"Alice","tags":["admin"]}}'
+after=$(redis-cli -h "$HOST" am.numchanges json_patch)
+assert_equals "$after" "$((before + 1))"
+echo "   ✓ Every op landed in one change"
+result=$(redis-cli -h "$HOST" am.applypatch json_patch '[{"op":"put","path":"user.name","value":"Bob"},{"op":"copy","path":"x"}]' 2>&1 || true)
+if echo "$result" | grep -q "patch entry 1"; then
+    echo "   ✓ Invalid op rejected"
+else
+    echo "   ✗ Invalid op not rejected (got: $result)"
+    exit 1
+fi
+result=$(redis-cli -h "$HOST" --raw am.gettext json_patch user.name)
+assert_equals "$result" "Alice"
+assert_equals "$(redis-cli -h "$HOST" am.numchanges json_patch)" "$after"
+echo "   ✓ Earlier ops in the rejected patch were rolled back"
+
+echo "Test 22: AM.TOJSON TAGGED round-trips through AM.FROMJSON TAGGED..."
+redis-cli -h "$HOST" del json_tagged json_tagged_copy > /dev/null
+redis-cli -h "$HOST" am.fromjson json_tagged '{"avatar":{"$bytes":"AAH/"}}' TAGGED > /dev/null
+redis-cli -h "$HOST" am.putcounter json_tagged views 5 > /dev/null
+redis-cli -h "$HOST" am.puttimestamp json_tagged created 1700000000000 > /dev/null
+tagged=$(redis-cli -h "$HOST" --raw am.tojson json_tagged SORTED TAGGED)
+assert_equals "$tagged" '{"avatar":{"$bytes":"AAH/"},"created":{"$timestamp":1700000000000},"views":{"$counter":5}}'
+echo "   ✓ Counter, timestamp and bytes exported as tags"
+redis-cli -h "$HOST" am.fromjson json_tagged_copy "$tagged" TAGGED > /dev/null
+redis-cli -h "$HOST" am.inccounter json_tagged_copy views 2 > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.getcounter json_tagged_copy views)" "7"
+assert_equals "$(redis-cli -h "$HOST" am.gettimestamp json_tagged_copy created)" "1700000000000"
+assert_equals "$(redis-cli -h "$HOST" --raw am.getraw json_tagged_copy avatar ENCODING hex)" "0001ff"
+echo "   ✓ Import restored the exact types"
+redis-cli -h "$HOST" am.fromjson json_tagged_copy "$tagged" > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.tojson json_tagged_copy SORTED)
+assert_equals "$result" "$tagged"
+echo "   ✓ Without TAGGED the tags stay ordinary maps"
+result=$(redis-cli -h "$HOST" --raw am.tojson json_tagged_copy TAGGED)
+redis-cli -h "$HOST" del json_tagged_copy > /dev/null
+redis-cli -h "$HOST" am.fromjson json_tagged_copy "$result" TAGGED > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.getcounter json_tagged_copy views)" ""
+assert_equals "$(redis-cli -h "$HOST" am.getint json_tagged_copy 'views.$counter')" "5"
+echo "   ✓ Maps that look like tags are escaped and stay maps"
+
+redis-cli -h "$HOST" am.putjson json_tagged stats '{"hits":{"$counter":1}}' TAGGED > /dev/null
+redis-cli -h "$HOST" am.inccounter json_tagged stats.hits 1 > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.getcounter json_tagged stats.hits)" "2"
+echo "   ✓ AM.PUTJSON TAGGED restores tags at a path"
+
 echo ""
 echo "✅ All JSON operation tests passed!"
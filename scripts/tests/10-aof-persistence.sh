@@ -214,5 +214,34 @@ else
     echo "   ⚠ Skipped restart test (Docker not available)"
 fi
 
+echo "Test 6: A document keeps its actor through an AOF rewrite..."
+redis-cli -h "$HOST" del aof_actor_test aof_actor_copy > /dev/null
+redis-cli -h "$HOST" am.new aof_actor_test ACTOR aabbccdd > /dev/null
+redis-cli -h "$HOST" am.puttext aof_actor_test status "draft" > /dev/null
+# The rewrite replays AM.LOAD ... ACTOR, which callers can use too
+redis-cli -h "$HOST" --raw am.save aof_actor_test > /tmp/aof_actor_test.am
+truncate -s -1 /tmp/aof_actor_test.am
+redis-cli -h "$HOST" --raw -x am.load aof_actor_copy ACTOR aabbccdd < /tmp/aof_actor_test.am > /dev/null
+redis-cli -h "$HOST" am.puttext aof_actor_copy status "copied" > /dev/null
+actor=$(redis-cli -h "$HOST" --raw am.lastwriter aof_actor_copy status | head -1)
+assert_equals "$actor" "aabbccdd"
+rm -f /tmp/aof_actor_test.am
+
+redis-cli -h "$HOST" BGREWRITEAOF > /dev/null
+sleep 2
+
+if restart_redis; then
+    redis-cli -h "$HOST" am.puttext aof_actor_test status "published" > /dev/null
+    actor=$(redis-cli -h "$HOST" --raw am.lastwriter aof_actor_test status | head -1)
+    if [ "$actor" = "aabbccdd" ]; then
+        echo "   ✓ New changes after AOF rewrite and restart keep the actor"
+    else
+        echo "   ✗ Actor changed after AOF rewrite and restart: $actor"
+        exit 1
+    fi
+else
+    echo "   ⚠ Skipped restart test (Docker not available)"
+fi
+
 echo ""
 echo "✅ All AOF persistence tests passed!"
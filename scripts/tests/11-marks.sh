@@ -274,7 +274,7 @@ result=$(redis-cli -h "$HOST" am.appenddouble audit15_list values inf 2>&1)
 echo "$result" | grep -qi "finite double"
 # Finite values still work.
 result=$(redis-cli -h "$HOST" am.appenddouble audit15_list values 1.5)
-assert_equals "$result" "OK"
+assert_equals "$result" "0"
 echo "   ✓ APPENDDOUBLE matches PUTDOUBLE rejection"
 
 # Audit-#16 regression: AM.MARKCREATE no longer auto-detects type. A
@@ -304,5 +304,16 @@ result=$(redis-cli -h "$HOST" am.markcreate audit16_doc body bad bool maybe 0 4
 echo "$result" | grep -qi "true/false"
 echo "   ✓ Unknown type and per-type parse failures return descriptive errors"
 
+echo "Test 23: AM.GETRICHTEXT returns text and marks together..."
+redis-cli -h "$HOST" del marks_rich > /dev/null
+redis-cli -h "$HOST" am.new marks_rich > /dev/null
+redis-cli -h "$HOST" am.puttext marks_rich content "Hello World" > /dev/null
+redis-cli -h "$HOST" am.markcreate marks_rich content bold bool true 6 11 > /dev/null
+result=$(redis-cli -h "$HOST" --raw am.getrichtext marks_rich content)
+assert_equals "$result" '{"marks":[{"end":11,"name":"bold","start":6,"value":true}],"text":"Hello World"}'
+result=$(redis-cli -h "$HOST" --raw am.getrichtext marks_rich missing)
+assert_equals "$result" ""
+echo "   ✓ AM.GETRICHTEXT combines text and marks"
+
 echo ""
 echo "✅ All marks tests passed!"
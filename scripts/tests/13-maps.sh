@@ -180,6 +180,61 @@ len=$(redis-cli -h "$HOST" am.maplen mapdoc10 outer.another)
 assert_equals "$len" "1"
 echo "   ✓ Empty nested maps work"
 
+# Test 11: Delete several paths at once
+echo "Test 11: Delete several paths at once..."
+redis-cli -h "$HOST" del mapdoc11 > /dev/null
+redis-cli -h "$HOST" am.new mapdoc11 > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc11 user.name "Alice" > /dev/null
+redis-cli -h "$HOST" am.putint mapdoc11 user.age 30 > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc11 user.address.city "Paris" > /dev/null
+before=$(redis-cli -h "$HOST" am.numchanges mapdoc11)
+# Missing paths and the already-deleted child count as 0
+removed=$(redis-cli -h "$HOST" am.del mapdoc11 user.name user.email user.address user.address.city)
+assert_equals "$removed" "2"
+after=$(redis-cli -h "$HOST" am.numchanges mapdoc11)
+assert_equals "$after" "$((before + 1))"
+len=$(redis-cli -h "$HOST" am.maplen mapdoc11 user)
+assert_equals "$len" "1"
+# Nothing to delete: no new change
+removed=$(redis-cli -h "$HOST" am.del mapdoc11 nope)
+assert_equals "$removed" "0"
+after2=$(redis-cli -h "$HOST" am.numchanges mapdoc11)
+assert_equals "$after2" "$after"
+echo "   ✓ AM.DEL removes multiple paths in one change"
+
+# Test 12: Container kind at a path
+echo "Test 12: Container kind at a path..."
+redis-cli -h "$HOST" del mapdoc12 > /dev/null
+redis-cli -h "$HOST" am.new mapdoc12 > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc12 user.name "Alice" > /dev/null
+redis-cli -h "$HOST" am.createlist mapdoc12 tags > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc12 note "hi" > /dev/null
+redis-cli -h "$HOST" am.splicetext mapdoc12 note 2 0 "!" > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 "")" "map"
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 user)" "map"
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 tags)" "list"
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 note)" "text"
+# Scalars and missing paths are null
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 user.name)" ""
+assert_equals "$(redis-cli -h "$HOST" am.objtype mapdoc12 missing)" ""
+echo "   ✓ AM.OBJTYPE reports map, list and text"
+
+# Test 13: Rename a key
+echo "Test 13: Rename a key..."
+redis-cli -h "$HOST" del mapdoc13 > /dev/null
+redis-cli -h "$HOST" am.new mapdoc13 > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc13 user.fullname "Alice" > /dev/null
+redis-cli -h "$HOST" am.puttext mapdoc13 user.email "a@example.com" > /dev/null
+redis-cli -h "$HOST" am.renamekey mapdoc13 user fullname name > /dev/null
+assert_equals "$(redis-cli -h "$HOST" am.gettext mapdoc13 user.name)" "Alice"
+result=$(redis-cli -h "$HOST" am.renamekey mapdoc13 user fullname other 2>&1)
+echo "$result" | grep -q "old-key does not exist" || { echo "   ✗ Expected an error for a missing key"; exit 1; }
+result=$(redis-cli -h "$HOST" am.renamekey mapdoc13 user name email 2>&1)
+echo "$result" | grep -q "new-key already exists" || { echo "   ✗ Expected an error for a taken key"; exit 1; }
+result=$(redis-cli -h "$HOST" am.renamekey mapdoc13 user.name a b 2>&1)
+echo "$result" | grep -q "not a map" || { echo "   ✗ Expected an error for a non-map parent"; exit 1; }
+echo "   ✓ AM.RENAMEKEY moves the value and names each failure"
+
 rm -f /tmp/map-saved.bin
 
 echo ""
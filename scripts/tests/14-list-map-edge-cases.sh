@@ -189,5 +189,23 @@ echo "   ✓ JSON export of complex nested structure works"
 
 rm -f /tmp/edge-saved.bin
 
+echo "Test 12: AM.LEN sizes lists and maps alike..."
+redis-cli -h "$HOST" del len_edge > /dev/null
+redis-cli -h "$HOST" am.new len_edge > /dev/null
+redis-cli -h "$HOST" am.putjson len_edge user '{"name":"Alice","tags":["a","b","c"],"empty":[]}' > /dev/null
+result=$(redis-cli -h "$HOST" am.len len_edge user.tags)
+assert_equals "$result" "3"
+result=$(redis-cli -h "$HOST" am.len len_edge user)
+assert_equals "$result" "3"
+result=$(redis-cli -h "$HOST" am.len len_edge user.empty)
+assert_equals "$result" "0"
+result=$(redis-cli -h "$HOST" am.len len_edge "")
+assert_equals "$result" "1"
+result=$(redis-cli -h "$HOST" am.len len_edge user.missing)
+assert_equals "$result" ""
+result=$(redis-cli -h "$HOST" am.len len_edge user.name 2>&1 || true)
+echo "$result" | grep -qi "not a list or map" || { echo "   ✗ Expected an error for a scalar, got: $result"; exit 1; }
+echo "   ✓ AM.LEN returns list lengths and map key counts, and rejects scalars"
+
 echo ""
 echo "✅ All list and map edge case tests passed!"
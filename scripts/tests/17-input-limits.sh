@@ -106,5 +106,31 @@ else
 fi
 echo "   ✓ Normal-sized inputs still work"
 
+# -----------------------------------------------------------------------------
+# Test 6: AM.NEW MAXBYTES rolls back writes over the document's budget
+# -----------------------------------------------------------------------------
+echo "Test 6: AM.NEW MAXBYTES rolls back over-budget writes..."
+redis-cli -h "$HOST" del budget-doc > /dev/null
+redis-cli -h "$HOST" am.new budget-doc MAXBYTES 4096 > /dev/null
+redis-cli -h "$HOST" am.puttext budget-doc name "Alice" > /dev/null
+before=$(redis-cli -h "$HOST" am.changes budget-doc COUNT)
+# Random bytes in base64 barely compress, so this is well over 4 KiB saved.
+big=$(head -c 6000 /dev/urandom | base64 | tr -d '\n')
+result=$(redis-cli -h "$HOST" am.puttext budget-doc bio "$big" 2>&1 || true)
+if echo "$result" | grep -q "over its MAXBYTES budget of 4096"; then
+    echo "   ✓ Over-budget write rejected"
+else
+    echo "   ✗ Expected a MAXBYTES error, got: $result"
+    exit 1
+fi
+bio=$(redis-cli -h "$HOST" am.gettext budget-doc bio)
+assert_equals "$bio" ""
+after=$(redis-cli -h "$HOST" am.changes budget-doc COUNT)
+assert_equals "$after" "$before" "rejected write must leave no change behind"
+redis-cli -h "$HOST" am.puttext budget-doc city "Paris" > /dev/null
+city=$(redis-cli -h "$HOST" am.gettext budget-doc city)
+assert_equals "$city" "Paris"
+echo "   ✓ Rejected write rolled back, smaller writes still succeed"
+
 echo ""
 echo "✅ All input-limit tests passed!"
@@ -4,8 +4,10 @@ Document synchronization tests for Redis-Automerge.
 Tests AM.APPLY functionality with proper binary change arrays.
 This is the key capability that bash tests couldn't properly exercise.
 """
-import pytest
 import asyncio
+import base64
+import json
+import pytest
 
 
 @pytest.mark.sync
@@ -360,3 +362,389 @@ async def test_complex_nested_structure_sync(redis_client, clean_redis):
     assert age == 30
     assert tags_len == 2
     assert views == 100
+
+
+@pytest.mark.sync
+async def test_apply_from_stream(redis_client, clean_redis):
+    """Test AM.APPLYSTREAM ingesting base64 changes from a Redis Stream."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'name', 'Alice')
+    await redis_client.execute_command('AM.PUTINT', 'source', 'age', 30)
+    changes = await redis_client.execute_command('AM.CHANGES', 'source')
+
+    # Seed the stream the way a pipeline would
+    for change in changes:
+        await redis_client.xadd('ingest', {'change': base64.b64encode(change)})
+
+    await redis_client.execute_command('AM.NEW', 'target')
+    last_id = await redis_client.execute_command('AM.APPLYSTREAM', 'target', 'ingest')
+    entries = await redis_client.xrange('ingest')
+    assert last_id == entries[-1][0]
+
+    assert await redis_client.execute_command('AM.GETTEXT', 'target', 'name') == b'Alice'
+    assert await redis_client.execute_command('AM.GETINT', 'target', 'age') == 30
+
+    # Resuming from the checkpoint only picks up newer entries
+    assert await redis_client.execute_command(
+        'AM.APPLYSTREAM', 'target', 'ingest', last_id) is None
+
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'name', 'Bob')
+    newest = (await redis_client.execute_command('AM.CHANGES', 'source'))[-1]
+    await redis_client.xadd('ingest', {'change': base64.b64encode(newest)})
+    next_id = await redis_client.execute_command(
+        'AM.APPLYSTREAM', 'target', 'ingest', last_id)
+    assert next_id != last_id
+    assert await redis_client.execute_command('AM.GETTEXT', 'target', 'name') == b'Bob'
+    assert await redis_client.execute_command('AM.NUMCHANGES', 'target') == 3
+
+
+@pytest.mark.sync
+async def test_apply_publishes_batch_message(redis_client, clean_redis):
+    """Test AM.APPLY publishing the whole batch as one message on batch:<key>."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'name', 'Alice')
+    await redis_client.execute_command('AM.PUTINT', 'source', 'age', 30)
+    await redis_client.execute_command('AM.PUTBOOL', 'source', 'active', 1)
+    changes = await redis_client.execute_command('AM.CHANGES', 'source')
+    assert len(changes) == 3
+
+    await redis_client.execute_command('AM.NEW', 'target')
+    pubsub = redis_client.pubsub()
+    await pubsub.subscribe('batch:target', 'changes:target')
+    # Drain the two subscribe confirmations
+    for _ in range(2):
+        await pubsub.get_message(timeout=1.0)
+
+    await redis_client.execute_command('AM.APPLY', 'target', *changes)
+
+    batches, singles = [], []
+    for _ in range(4):
+        message = await pubsub.get_message(ignore_subscribe_messages=True, timeout=1.0)
+        assert message is not None
+        if message['channel'] == b'batch:target':
+            batches.append(message['data'])
+        else:
+            singles.append(message['data'])
+    await pubsub.aclose()
+
+    # Per-change publishing is unchanged; the batch carries them all at once
+    assert len(singles) == 3
+    assert len(batches) == 1
+    batch = [base64.b64decode(c) for c in json.loads(batches[0])]
+    assert batch == list(changes)
+    assert [base64.b64decode(s) for s in singles] == batch
+
+
+@pytest.mark.sync
+async def test_diffkeys_reports_one_sided_changes(redis_client, clean_redis):
+    """Test AM.DIFFKEYS listing the changes each of two documents lacks."""
+    await redis_client.execute_command('AM.NEW', 'primary')
+    await redis_client.execute_command('AM.PUTTEXT', 'primary', 'title', 'base')
+    await redis_client.execute_command('AM.NEW', 'replica')
+    await redis_client.execute_command(
+        'AM.APPLY', 'replica', *await redis_client.execute_command('AM.CHANGES', 'primary'))
+    assert await redis_client.execute_command('AM.DIFFKEYS', 'primary', 'replica') == [[], []]
+
+    # Diverge: two writes on the primary, one on the replica
+    await redis_client.execute_command('AM.PUTINT', 'primary', 'a', 1)
+    await redis_client.execute_command('AM.PUTINT', 'primary', 'a', 2)
+    await redis_client.execute_command('AM.PUTINT', 'replica', 'b', 1)
+    primary_heads = await redis_client.execute_command('AM.WAITCHANGE', 'primary', 0)
+    replica_heads = await redis_client.execute_command('AM.WAITCHANGE', 'replica', 0)
+
+    a_only, b_only = await redis_client.execute_command('AM.DIFFKEYS', 'primary', 'replica')
+    assert len(a_only) == 2
+    assert a_only[-1] == primary_heads[0]
+    assert b_only == replica_heads
+
+    with pytest.raises(Exception, match='no such key'):
+        await redis_client.execute_command('AM.DIFFKEYS', 'primary', 'missing')
+
+
+@pytest.mark.sync
+async def test_apply_from_source_is_not_republished(redis_client, clean_redis):
+    """Test AM.APPLY FROM suppressing the echo of a peer's own changes."""
+    await redis_client.execute_command('AM.NEW', 'peer')
+    await redis_client.execute_command('AM.PUTTEXT', 'peer', 'name', 'Alice')
+    await redis_client.execute_command('AM.PUTINT', 'peer', 'age', 30)
+    first, second = await redis_client.execute_command('AM.CHANGES', 'peer')
+
+    await redis_client.execute_command('AM.NEW', 'hub')
+    pubsub = redis_client.pubsub()
+    await pubsub.subscribe('changes:hub', 'batch:hub')
+    for _ in range(2):
+        await pubsub.get_message(timeout=1.0)
+
+    # Tagged with FROM: applied, but nothing is published back
+    await redis_client.execute_command('AM.APPLY', 'hub', 'FROM', 'self', first)
+    assert await redis_client.execute_command('AM.GETTEXT', 'hub', 'name') == b'Alice'
+    assert await pubsub.get_message(ignore_subscribe_messages=True, timeout=0.5) is None
+
+    # Without FROM the change is published as before
+    await redis_client.execute_command('AM.APPLY', 'hub', second)
+    message = await pubsub.get_message(ignore_subscribe_messages=True, timeout=1.0)
+    assert message is not None
+    assert message['channel'] == b'changes:hub'
+    assert base64.b64decode(message['data']) == second
+    await pubsub.aclose()
+
+    with pytest.raises(Exception, match='FROM requires'):
+        await redis_client.execute_command('AM.APPLY', 'hub', 'FROM', 'self')
+
+
+@pytest.mark.sync
+async def test_load_document_in_chunks(redis_client, clean_redis):
+    """Test AM.LOADCHUNK/AM.LOADCOMMIT rebuilding a saved document."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'name', 'Alice')
+    await redis_client.execute_command('AM.PUTINT', 'source', 'age', 30)
+    saved = await redis_client.execute_command('AM.SAVE', 'source')
+
+    third = len(saved) // 3
+    chunks = [saved[:third], saved[third:2 * third], saved[2 * third:]]
+    for seq, chunk in enumerate(chunks):
+        count = await redis_client.execute_command('AM.LOADCHUNK', 'copy', seq, chunk)
+        assert count == seq + 1
+    assert await redis_client.execute_command('AM.LOADCOMMIT', 'copy') == b'OK'
+
+    assert await redis_client.execute_command('AM.GETTEXT', 'copy', 'name') == b'Alice'
+    assert await redis_client.execute_command('AM.GETINT', 'copy', 'age') == 30
+
+    # The session is gone after a commit
+    with pytest.raises(Exception, match='no load session'):
+        await redis_client.execute_command('AM.LOADCOMMIT', 'copy')
+
+    # Chunks out of order are rejected
+    await redis_client.execute_command('AM.LOADCHUNK', 'copy', 0, chunks[0])
+    with pytest.raises(Exception, match='expected chunk sequence 1'):
+        await redis_client.execute_command('AM.LOADCHUNK', 'copy', 2, chunks[2])
+
+
+@pytest.mark.sync
+async def test_changes_paginated_backfill(redis_client, clean_redis):
+    """Test paging through a long history with AM.CHANGES LIMIT."""
+    await redis_client.execute_command('AM.NEW', 'history')
+    for i in range(25):
+        await redis_client.execute_command('AM.PUTINT', 'history', f'n{i}', i)
+    everything = await redis_client.execute_command('AM.CHANGES', 'history')
+
+    pages = []
+    cursor, page = await redis_client.execute_command('AM.CHANGES', 'history', 'LIMIT', 10)
+    pages.append(page)
+    while cursor is not None:
+        cursor, page = await redis_client.execute_command(
+            'AM.CHANGES', 'history', 'LIMIT', 10, 'AFTER', cursor)
+        pages.append(page)
+
+    assert [len(page) for page in pages] == [10, 10, 6]
+    assert [change for page in pages for change in page] == everything
+
+    # A fresh document built from the pages converges with the original
+    await redis_client.execute_command('AM.NEW', 'replica')
+    for page in pages:
+        await redis_client.execute_command('AM.APPLY', 'replica', *page)
+    assert await redis_client.execute_command('AM.GETINT', 'replica', 'n24') == 24
+    assert await redis_client.execute_command('AM.DIFFKEYS', 'history', 'replica') == [[], []]
+
+    with pytest.raises(Exception, match='COUNT'):
+        await redis_client.execute_command('AM.CHANGES', 'history', 'COUNT', 'LIMIT', 10)
+
+
+@pytest.mark.sync
+async def test_apply_error_names_the_bad_change(redis_client, clean_redis):
+    """Test AM.APPLY reporting which change in a batch failed to decode."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'name', 'Alice')
+    await redis_client.execute_command('AM.PUTINT', 'source', 'age', 30)
+    first, second = await redis_client.execute_command('AM.CHANGES', 'source')
+
+    await redis_client.execute_command('AM.NEW', 'target')
+    with pytest.raises(Exception, match=r'invalid change at index 1 \(7 bytes\)'):
+        await redis_client.execute_command('AM.APPLY', 'target', first, b'garbage', second)
+
+    # Nothing from the batch was applied
+    assert await redis_client.execute_command('AM.GETTEXT', 'target', 'name') is None
+
+    # The index counts changes only, not the FROM tag
+    with pytest.raises(Exception, match='invalid change at index 0'):
+        await redis_client.execute_command('AM.APPLY', 'target', 'FROM', 'peer', b'garbage')
+
+
+@pytest.mark.sync
+async def test_apply_fires_an_event_per_actor(redis_client, clean_redis):
+    """Test AM.APPLY firing am.apply.<actor> keyspace events (apply-actor-events=yes)."""
+    changes = []
+    for key, actor in (('east', 'aa' * 16), ('west', 'bb' * 16)):
+        await redis_client.execute_command('AM.NEW', key, 'ACTOR', actor)
+        await redis_client.execute_command('AM.PUTTEXT', key, 'region', key)
+        await redis_client.execute_command('AM.PUTINT', key, 'n', 1)
+        changes += await redis_client.execute_command('AM.CHANGES', key)
+
+    await redis_client.execute_command('AM.NEW', 'hub')
+    pubsub = redis_client.pubsub()
+    await pubsub.psubscribe('__keyevent@*__:am.apply*')
+    await pubsub.get_message(timeout=1.0)
+
+    await redis_client.execute_command('AM.APPLY', 'hub', *changes)
+
+    events = []
+    while True:
+        message = await pubsub.get_message(ignore_subscribe_messages=True, timeout=0.5)
+        if message is None:
+            break
+        assert message['data'] == b'hub'
+        events.append(message['channel'].split(b':', 1)[1])
+    await pubsub.aclose()
+
+    # The plain event, then one per authoring actor, however many changes each wrote
+    assert events == [b'am.apply', b'am.apply.aaaaaaaa', b'am.apply.bbbbbbbb']
+
+
+@pytest.mark.sync
+async def test_lastwriter_reports_the_authoring_change(redis_client, clean_redis):
+    """Test AM.LASTWRITER naming the actor and change behind a value."""
+    await redis_client.execute_command('AM.NEW', 'ops', 'ACTOR', 'aa' * 16)
+    await redis_client.execute_command('AM.PUTINT', 'ops', 'config.port', 80)
+    ops_heads = await redis_client.execute_command('AM.WAITCHANGE', 'ops', 0)
+    assert await redis_client.execute_command('AM.LASTWRITER', 'ops', 'config.port') == [
+        b'aa' * 16, ops_heads[0], 0]
+
+    # A later change from another actor takes over, once applied
+    await redis_client.execute_command('AM.NEW', 'dev', 'ACTOR', 'bb' * 16)
+    await redis_client.execute_command(
+        'AM.APPLY', 'dev', *await redis_client.execute_command('AM.CHANGES', 'ops'))
+    await redis_client.execute_command('AM.PUTINT', 'dev', 'config.port', 8080)
+    dev_heads = await redis_client.execute_command('AM.WAITCHANGE', 'dev', 0)
+    await redis_client.execute_command(
+        'AM.APPLY', 'ops', *await redis_client.execute_command('AM.CHANGES', 'dev'))
+    actor, change_hash, timestamp = await redis_client.execute_command(
+        'AM.LASTWRITER', 'ops', 'config.port')
+    assert (actor, change_hash, timestamp) == (b'bb' * 16, dev_heads[0], 0)
+
+    assert await redis_client.execute_command('AM.LASTWRITER', 'ops', 'config.missing') is None
+
+
+@pytest.mark.sync
+async def test_apply_verbose_reports_each_change(redis_client, clean_redis):
+    """Test AM.APPLY VERBOSE returning applied/duplicate per input change."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'a', '1')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'b', '2')
+    first, second = await redis_client.execute_command('AM.CHANGES', 'source')
+
+    await redis_client.execute_command('AM.NEW', 'target')
+    await redis_client.execute_command('AM.APPLY', 'target', first)
+
+    # The first change is already present, and the second is repeated
+    statuses = await redis_client.execute_command(
+        'AM.APPLY', 'target', 'VERBOSE', first, second, second)
+    assert statuses == [b'duplicate', b'applied', b'duplicate']
+
+    # VERBOSE combines with FROM, and a batch with nothing new still replies
+    statuses = await redis_client.execute_command(
+        'AM.APPLY', 'target', 'VERBOSE', 'FROM', 'gateway', first, second)
+    assert statuses == [b'duplicate', b'duplicate']
+
+    # Without the flag the reply is unchanged
+    assert await redis_client.execute_command('AM.APPLY', 'target', second) == b'OK'
+
+
+@pytest.mark.sync
+async def test_pathops_shows_concurrent_writes(redis_client, clean_redis):
+    """Test AM.PATHOPS listing both actors' ops on a conflicted field."""
+    await redis_client.execute_command('AM.NEW', 'alice', 'ACTOR', 'aa' * 16)
+    await redis_client.execute_command('AM.PUTINT', 'alice', 'config.port', 80)
+    await redis_client.execute_command('AM.NEW', 'bob', 'ACTOR', 'bb' * 16)
+    await redis_client.execute_command(
+        'AM.APPLY', 'bob', *await redis_client.execute_command('AM.CHANGES', 'alice'))
+
+    # Both write the field concurrently, then alice receives bob's change
+    await redis_client.execute_command('AM.PUTINT', 'alice', 'config.port', 8080)
+    await redis_client.execute_command('AM.PUTINT', 'bob', 'config.port', 9090)
+    bob_changes = await redis_client.execute_command('AM.CHANGES', 'bob')
+    await redis_client.execute_command('AM.APPLY', 'alice', *bob_changes)
+
+    ops = await redis_client.execute_command('AM.PATHOPS', 'alice', 'config.port')
+    assert ops[0] == [b'put', b'aa' * 16, 1, b'80']
+    assert sorted(ops[1:]) == [[b'put', b'aa' * 16, 2, b'8080'],
+                               [b'put', b'bb' * 16, 1, b'9090']]
+
+    # Unknown parents reply null
+    assert await redis_client.execute_command('AM.PATHOPS', 'alice', 'nope.port') is None
+
+
+@pytest.mark.sync
+async def test_apply_publishes_a_repeated_change_once(redis_client, clean_redis):
+    """Test AM.APPLY counting a change repeated within one batch only once."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'a', '1')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'b', '2')
+    first, second = await redis_client.execute_command('AM.CHANGES', 'source')
+
+    await redis_client.execute_command('AM.NEW', 'target')
+    await redis_client.execute_command('AM.APPLY', 'target', first)
+    pubsub = redis_client.pubsub()
+    await pubsub.subscribe('batch:target', 'changes:target')
+    for _ in range(2):
+        await pubsub.get_message(timeout=1.0)
+
+    # first is already stored and second is sent twice
+    await redis_client.execute_command('AM.APPLY', 'target', first, second, second)
+
+    batches, singles = [], []
+    while True:
+        message = await pubsub.get_message(ignore_subscribe_messages=True, timeout=0.5)
+        if message is None:
+            break
+        if message['channel'] == b'batch:target':
+            batches.append(message['data'])
+        else:
+            singles.append(message['data'])
+    await pubsub.aclose()
+
+    assert [base64.b64decode(s) for s in singles] == [second]
+    assert len(batches) == 1
+    assert [base64.b64decode(c) for c in json.loads(batches[0])] == [second]
+    assert await redis_client.execute_command('AM.NUMCHANGES', 'target') == 2
+
+
+@pytest.mark.sync
+async def test_apply_requestdeps_publishes_missing_hashes(redis_client, clean_redis):
+    """Test AM.APPLY REQUESTDEPS asking for missing dependencies on need:<key>."""
+    await redis_client.execute_command('AM.NEW', 'source')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'a', '1')
+    await redis_client.execute_command('AM.PUTTEXT', 'source', 'b', '2')
+    first, second = await redis_client.execute_command('AM.CHANGES', 'source')
+
+    # The hash of the first change, taken from a document that holds only it
+    await redis_client.execute_command('AM.NEW', 'empty')
+    await redis_client.execute_command('AM.NEW', 'only_first')
+    await redis_client.execute_command('AM.APPLY', 'only_first', first)
+    [first_hash], _ = await redis_client.execute_command(
+        'AM.DIFFKEYS', 'only_first', 'empty')
+
+    await redis_client.execute_command('AM.NEW', 'plain')
+    await redis_client.execute_command('AM.NEW', 'target')
+    pubsub = redis_client.pubsub()
+    await pubsub.subscribe('need:plain', 'need:target')
+    for _ in range(2):
+        await pubsub.get_message(timeout=1.0)
+
+    # Without the flag nothing is requested
+    await redis_client.execute_command('AM.APPLY', 'plain', second)
+    assert await pubsub.get_message(ignore_subscribe_messages=True, timeout=0.5) is None
+
+    # With it, the missing first change is requested; the second stays pending
+    reply = await redis_client.execute_command('AM.APPLY', 'target', 'REQUESTDEPS', second)
+    assert reply == b'OK'
+    message = await pubsub.get_message(ignore_subscribe_messages=True, timeout=1.0)
+    assert message is not None
+    assert message['channel'] == b'need:target'
+    assert json.loads(message['data']) == [first_hash.hex()]
+    assert await redis_client.execute_command('AM.PENDING', 'target') == [1, [first_hash]]
+
+    # Once the peer supplies it, nothing is missing and nothing is requested
+    await redis_client.execute_command('AM.APPLY', 'target', 'REQUESTDEPS', first)
+    assert await pubsub.get_message(ignore_subscribe_messages=True, timeout=0.5) is None
+    await pubsub.aclose()
+    assert await redis_client.execute_command('AM.GETTEXT', 'target', 'b') == b'2'